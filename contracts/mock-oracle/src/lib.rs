@@ -0,0 +1,80 @@
+// mock-oracle is an oracle contract returning the same owner-set price for
+// every pair. It implements the `OracleQueryMsg` expected by swaprouter's
+// `PriceSource::Oracle` and is only meant to be used in tests.
+// The oracle messages are copied rather than imported, depending on swaprouter
+// would enable its `library` feature for every workspace build.
+
+use cosmwasm_schema::{cw_serde, QueryResponses};
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    to_json_binary, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
+};
+use cw_storage_plus::Item;
+
+/// Message type for `instantiate` entry_point
+#[cw_serde]
+pub struct InstantiateMsg {
+    pub price: Decimal,
+}
+
+/// Message type for `execute` entry_point
+#[cw_serde]
+pub enum ExecuteMsg {
+    SetPrice { price: Decimal },
+}
+
+/// Message type for `query` entry_point, the `OracleQueryMsg` of swaprouter.
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum OracleQueryMsg {
+    /// Price of one unit of `base_denom` expressed in `quote_denom`.
+    #[returns(OraclePriceResponse)]
+    Price {
+        base_denom: String,
+        quote_denom: String,
+    },
+}
+
+#[cw_serde]
+pub struct OraclePriceResponse {
+    pub price: Decimal,
+}
+
+// PRICE stores the price returned for every pair.
+pub const PRICE: Item<Decimal> = Item::new("price");
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> StdResult<Response> {
+    PRICE.save(deps.storage, &msg.price)?;
+    Ok(Response::new().add_attribute("method", "instantiate"))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: ExecuteMsg,
+) -> StdResult<Response> {
+    match msg {
+        ExecuteMsg::SetPrice { price } => {
+            PRICE.save(deps.storage, &price)?;
+            Ok(Response::new().add_attribute("action", "set_price"))
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: OracleQueryMsg) -> StdResult<Binary> {
+    match msg {
+        OracleQueryMsg::Price { .. } => to_json_binary(&OraclePriceResponse {
+            price: PRICE.load(deps.storage)?,
+        }),
+    }
+}
@@ -3,17 +3,39 @@ use std::str::FromStr;
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    coins, to_binary, BankMsg, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdResult,
-    SubMsgResponse, SubMsgResult, Uint128,
+    coin, coins, to_json_binary, Addr, BankMsg, Binary, Coin, Decimal, Deps, DepsMut, Env, Event,
+    MessageInfo, Reply, Response, StdResult, SubMsg, SubMsgResponse, SubMsgResult, Uint128,
 };
 use cw2::set_contract_version;
-use osmosis_std::types::osmosis::gamm::v1beta1::MsgSwapExactAmountInResponse;
+use osmosis_std::types::osmosis::poolmanager::v1beta1::{
+    MsgSwapExactAmountInResponse, SwapAmountInRoute,
+};
 
 use crate::error::ContractError;
-use crate::execute::{set_route, swap};
-use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
-use crate::query::{query_owner, query_route};
-use crate::state::{SwapMsgReplyState, OWNER, SWAP_REPLY_STATES};
+use crate::execute::{
+    cancel_proposed_route, disable_pool, execute_proposed_route, propose_route,
+    prune_invalid_routes, remove_denom_alias, remove_expired_routes, restore_route,
+    set_denom_alias, set_fallback_routes, set_pair_config, set_rate_limit, set_route, set_routes,
+    swap, update_config, update_denom_list,
+};
+use crate::helpers::{
+    generate_hop_swap_msg, generate_swap_submsg, record_swap_history, release_rate_limit,
+    update_pair_stats, update_price_accumulator, validate_and_save_routes, validate_fallback_route,
+    validate_pair_config,
+};
+use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg, SwapType};
+use crate::query::{
+    query_all_pair_stats, query_config, query_denom_aliases, query_denom_list,
+    query_display_amount, query_export_routes, query_fallback_routes, query_list_routes,
+    query_owner, query_pair_config, query_pair_stats, query_proposed_routes,
+    query_remaining_capacity, query_route, query_route_dependents, query_route_health,
+    query_route_history, query_router_twap, query_routes_by_pool, query_swap_history,
+    query_swap_history_by_pair, query_swap_history_by_sender, query_validate_route,
+};
+use crate::state::{
+    Config, StepWiseState, SwapMsgReplyState, SwapRecord, CONFIG, OWNER, PAIR_CONFIGS,
+    SWAP_REPLY_STATES,
+};
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:swaprouter";
@@ -21,12 +43,29 @@ const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 // Msg Reply IDs
 pub const SWAP_REPLY_ID: u64 = 1u64;
+pub const STEP_SWAP_REPLY_ID: u64 = 2u64;
+
+// Number of swap history records kept unless configured otherwise.
+pub const DEFAULT_SWAP_HISTORY_RETENTION: u64 = 10_000;
+// Most swap history records pruned per swap, so that lowering the retention
+// spreads the pruning over the following swaps.
+pub const SWAP_HISTORY_PRUNE_LIMIT: usize = 10;
+// Number of fixed buckets a rolling rate limit window is tracked in.
+pub const RATE_LIMIT_BUCKETS: u64 = 10;
+// Most stale rate limit volumes pruned per swap, spread over the following swaps.
+pub const RATE_LIMIT_PRUNE_LIMIT: usize = 10;
+// Longest router TWAP window (48 hours) unless configured otherwise.
+pub const DEFAULT_ROUTER_TWAP_MAX_WINDOW: u64 = 48 * 60 * 60;
+// Maximum number of hops in a route unless configured otherwise.
+pub const DEFAULT_MAX_ROUTE_HOPS: u64 = 5;
+// Largest denom alias exponent, the number of decimal places of `Decimal`.
+pub const MAX_DENOM_EXPONENT: u32 = 18;
 
 /// Handling contract instantiation
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
-    deps: DepsMut,
-    _env: Env,
+    mut deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
@@ -36,11 +75,36 @@ pub fn instantiate(
 
     OWNER.save(deps.storage, &owner_address)?;
 
+    let mut config = Config {
+        swap_history_retention: DEFAULT_SWAP_HISTORY_RETENTION,
+        router_twap_max_window: DEFAULT_ROUTER_TWAP_MAX_WINDOW,
+        max_spot_twap_deviation_percentage: Decimal::zero(),
+        max_route_hops: DEFAULT_MAX_ROUTE_HOPS,
+        min_pool_liquidity: Uint128::zero(),
+        route_timelock_seconds: 0,
+        denom_allowlist_enabled: false,
+    };
+    msg.config.apply(&mut config);
+    CONFIG.save(deps.storage, &config)?;
+
+    // routes are validated against the config above.
+    let routes = validate_and_save_routes(deps.branch(), msg.routes, &info.sender, &env.block)?;
+
+    for entry in msg.pair_configs {
+        validate_pair_config(deps.as_ref(), &entry.pair_config)?;
+        PAIR_CONFIGS.save(
+            deps.storage,
+            (&entry.input_denom, &entry.output_denom),
+            &entry.pair_config,
+        )?;
+    }
+
     // With `Response` type, it is possible to dispatch message to invoke external logic.
     // See: https://github.com/CosmWasm/cosmwasm/blob/main/SEMANTICS.md#dispatching-messages
     Ok(Response::new()
         .add_attribute("method", "instantiate")
-        .add_attribute("owner", info.sender))
+        .add_attribute("owner", info.sender)
+        .add_attribute("routes", routes.to_string()))
 }
 
 /// Handling contract execution
@@ -56,32 +120,214 @@ pub fn execute(
             input_denom,
             output_denom,
             pool_route,
-        } => set_route(deps, info, input_denom, output_denom, pool_route),
+            bidirectional,
+            expires_at,
+            propagate,
+        } => set_route(
+            deps,
+            env,
+            info,
+            input_denom,
+            output_denom,
+            pool_route,
+            bidirectional,
+            expires_at,
+            propagate,
+        ),
+        ExecuteMsg::SetRoutes { routes } => set_routes(deps, env, info, routes),
         ExecuteMsg::Swap {
             input_coin,
             output_denom,
             swap_type,
-        } => swap(deps, env, info, input_coin, output_denom, swap_type),
+            deadline,
+            execution_mode,
+        } => swap(
+            deps,
+            env,
+            info,
+            input_coin,
+            output_denom,
+            swap_type,
+            deadline,
+            execution_mode,
+        ),
+        ExecuteMsg::UpdateConfig(update) => update_config(deps, info, update),
+        ExecuteMsg::SetPairConfig {
+            input_denom,
+            output_denom,
+            pair_config,
+        } => set_pair_config(deps, info, input_denom, output_denom, pair_config),
+        ExecuteMsg::DisablePool { pool_id } => disable_pool(deps, env, info, pool_id),
+        ExecuteMsg::PruneInvalidRoutes { start_after, limit } => {
+            prune_invalid_routes(deps, start_after, limit)
+        }
+        ExecuteMsg::RestoreRoute {
+            input_denom,
+            output_denom,
+        } => restore_route(deps, info, input_denom, output_denom),
+        ExecuteMsg::RemoveExpiredRoutes { start_after, limit } => {
+            remove_expired_routes(deps, env, info, start_after, limit)
+        }
+        ExecuteMsg::SetFallbackRoutes {
+            input_denom,
+            output_denom,
+            fallback_routes,
+        } => set_fallback_routes(deps, info, input_denom, output_denom, fallback_routes),
+        ExecuteMsg::SetRateLimit { denom, rate_limit } => {
+            set_rate_limit(deps, info, denom, rate_limit)
+        }
+        ExecuteMsg::UpdateDenomList { list, add, remove } => {
+            update_denom_list(deps, info, list, add, remove)
+        }
+        ExecuteMsg::SetDenomAlias { symbol, alias } => set_denom_alias(deps, info, symbol, alias),
+        ExecuteMsg::RemoveDenomAlias { symbol } => remove_denom_alias(deps, info, symbol),
+        ExecuteMsg::ProposeRoute {
+            input_denom,
+            output_denom,
+            pool_route,
+            bidirectional,
+            expires_at,
+            propagate,
+        } => propose_route(
+            deps,
+            env,
+            info,
+            input_denom,
+            output_denom,
+            pool_route,
+            bidirectional,
+            expires_at,
+            propagate,
+        ),
+        ExecuteMsg::ExecuteProposedRoute {
+            input_denom,
+            output_denom,
+        } => execute_proposed_route(deps, env, info, input_denom, output_denom),
+        ExecuteMsg::CancelProposedRoute {
+            input_denom,
+            output_denom,
+        } => cancel_proposed_route(deps, info, input_denom, output_denom),
     }
 }
 
 /// Handling contract query
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
-        QueryMsg::GetOwner {} => to_binary(&query_owner(deps)?),
+        QueryMsg::GetOwner {} => to_json_binary(&query_owner(deps)?),
         QueryMsg::GetRoute {
             input_denom,
             output_denom,
-        } => to_binary(&query_route(deps, input_denom, output_denom)?),
+        } => to_json_binary(&query_route(deps, env, input_denom, output_denom)?),
+        QueryMsg::ListRoutes { start_after, limit } => {
+            to_json_binary(&query_list_routes(deps, env, start_after, limit)?)
+        }
+        QueryMsg::RouteHistory {
+            input_denom,
+            output_denom,
+            start_after,
+            limit,
+        } => to_json_binary(&query_route_history(
+            deps,
+            input_denom,
+            output_denom,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::RoutesByPool { pool_id } => to_json_binary(&query_routes_by_pool(deps, pool_id)?),
+        QueryMsg::RouteDependents {
+            input_denom,
+            output_denom,
+        } => to_json_binary(&query_route_dependents(deps, input_denom, output_denom)?),
+        QueryMsg::RouteHealth { start_after, limit } => {
+            to_json_binary(&query_route_health(deps, start_after, limit)?)
+        }
+        QueryMsg::GetFallbackRoutes {
+            input_denom,
+            output_denom,
+        } => to_json_binary(&query_fallback_routes(deps, input_denom, output_denom)?),
+        QueryMsg::ProposedRoutes { start_after, limit } => {
+            to_json_binary(&query_proposed_routes(deps, start_after, limit)?)
+        }
+        QueryMsg::ValidateRoute {
+            input_denom,
+            output_denom,
+            pool_route,
+        } => to_json_binary(&query_validate_route(
+            deps,
+            input_denom,
+            output_denom,
+            pool_route,
+        )?),
+        QueryMsg::ExportRoutes {} => to_json_binary(&query_export_routes(deps, env)?),
+        QueryMsg::RemainingCapacity { denom, sender } => {
+            to_json_binary(&query_remaining_capacity(deps, env, denom, sender)?)
+        }
+        QueryMsg::DenomList {
+            list,
+            start_after,
+            limit,
+        } => to_json_binary(&query_denom_list(deps, list, start_after, limit)?),
+        QueryMsg::DenomAliases { start_after, limit } => {
+            to_json_binary(&query_denom_aliases(deps, start_after, limit)?)
+        }
+        QueryMsg::DisplayAmount { coin } => to_json_binary(&query_display_amount(deps, coin)?),
+        QueryMsg::GetConfig {} => to_json_binary(&query_config(deps)?),
+        QueryMsg::GetPairConfig {
+            input_denom,
+            output_denom,
+        } => to_json_binary(&query_pair_config(deps, input_denom, output_denom)?),
+        QueryMsg::SwapHistory { start_after, limit } => {
+            to_json_binary(&query_swap_history(deps, start_after, limit)?)
+        }
+        QueryMsg::SwapHistoryBySender {
+            sender,
+            start_after,
+            limit,
+        } => to_json_binary(&query_swap_history_by_sender(
+            deps,
+            sender,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::SwapHistoryByPair {
+            input_denom,
+            output_denom,
+            start_after,
+            limit,
+        } => to_json_binary(&query_swap_history_by_pair(
+            deps,
+            input_denom,
+            output_denom,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::PairStats {
+            input_denom,
+            output_denom,
+        } => to_json_binary(&query_pair_stats(deps, input_denom, output_denom)?),
+        QueryMsg::AllPairStats { start_after, limit } => {
+            to_json_binary(&query_all_pair_stats(deps, start_after, limit)?)
+        }
+        QueryMsg::RouterTwap {
+            input_denom,
+            output_denom,
+            window_seconds,
+        } => to_json_binary(&query_router_twap(
+            deps,
+            env,
+            input_denom,
+            output_denom,
+            window_seconds,
+        )?),
     }
 }
 
 /// Handling submessage reply.
 /// For more info on submessage and reply, see https://github.com/CosmWasm/cosmwasm/blob/main/SEMANTICS.md#submessages
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
-    if msg.id == SWAP_REPLY_ID {
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    if msg.id == SWAP_REPLY_ID || msg.id == STEP_SWAP_REPLY_ID {
         // get intermediate swap reply state. Error if not found.
         let swap_msg_state = SWAP_REPLY_STATES.load(deps.storage, msg.id)?;
 
@@ -89,17 +335,24 @@ pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractE
         SWAP_REPLY_STATES.remove(deps.storage, msg.id);
 
         // call reply function to handle the swap return
-        handle_swap_reply(msg, swap_msg_state)
+        if msg.id == SWAP_REPLY_ID {
+            handle_swap_reply(deps, env, msg, swap_msg_state)
+        } else {
+            handle_step_swap_reply(deps, env, msg, swap_msg_state)
+        }
     } else {
         Ok(Response::new())
     }
 }
 
 // handle_swap_reply deserializes the response from Osmosis chain
-// If the response is successful and swap is complete, send
-// the swapped token to the original user who initiated the swap.
-// Otherwise, return contract error.
+// If the response is successful and swap is complete, settle the swap.
+// If the swap failed, retry it through the next fallback route.
+// If all routes failed and the swap is best-effort, refund the input to the original sender.
+// Otherwise, return contract error with the failures of all routes.
 pub fn handle_swap_reply(
+    deps: DepsMut,
+    env: Env,
     msg: Reply,
     swap_msg_reply_state: SwapMsgReplyState,
 ) -> Result<Response, ContractError> {
@@ -110,26 +363,195 @@ pub fn handle_swap_reply(
         // Retrieve swapped amount.
         let amount = Uint128::from_str(&res.token_out_amount)?;
 
-        // Retrieve swapped denom from reply state.
-        let send_denom = &swap_msg_reply_state
+        // Retrieve the swapped in token from reply state.
+        let token_in = swap_msg_reply_state
             .swap_msg
-            .routes
-            .last()
-            .unwrap()
-            .token_out_denom;
-
-        // Send the swapped token from contract to the original
-        // user who initiated the swap.
-        let bank_msg = BankMsg::Send {
-            to_address: swap_msg_reply_state.original_sender.into_string(),
-            amount: coins(amount.u128(), send_denom),
-        };
+            .token_in
+            .clone()
+            .unwrap_or_default();
+        let input_coin = coin(Uint128::from_str(&token_in.amount)?.u128(), token_in.denom);
+
+        return settle_swap(
+            deps,
+            env,
+            swap_msg_reply_state.original_sender,
+            input_coin,
+            amount,
+            &swap_msg_reply_state.swap_msg.routes,
+            swap_msg_reply_state.swap_type,
+        );
+    }
+
+    let mut swap_msg_reply_state = swap_msg_reply_state;
+    let pool_ids = route_pool_ids(&swap_msg_reply_state.swap_msg.routes);
+    swap_msg_reply_state.failures.push(format!(
+        "pool route {:?}: {}",
+        pool_ids,
+        msg.result.unwrap_err()
+    ));
+
+    let token_in = swap_msg_reply_state
+        .swap_msg
+        .token_in
+        .clone()
+        .unwrap_or_default();
+    let input_coin = coin(Uint128::from_str(&token_in.amount)?.u128(), token_in.denom);
+    let output_denom = swap_msg_reply_state
+        .swap_msg
+        .routes
+        .last()
+        .map(|route_part| route_part.token_out_denom.clone())
+        .unwrap_or_default();
+
+    // Retry the swap through the next fallback route with the same input and minimum output,
+    // skipping the fallback routes that fail the checks of the primary route.
+    while !swap_msg_reply_state.fallback_routes.is_empty() {
+        let route = swap_msg_reply_state.fallback_routes.remove(0);
+        if let Err(err) = validate_fallback_route(
+            deps.as_ref(),
+            &input_coin,
+            &output_denom,
+            &swap_msg_reply_state.swap_type,
+            &route,
+            env.block.time,
+        ) {
+            swap_msg_reply_state.failures.push(format!(
+                "pool route {:?}: {}",
+                route_pool_ids(&route),
+                err
+            ));
+            continue;
+        }
+        swap_msg_reply_state.swap_msg.routes = route;
+
+        // save intermediate state for the reply of the fallback swap
+        SWAP_REPLY_STATES.save(deps.storage, SWAP_REPLY_ID, &swap_msg_reply_state)?;
 
-        // Success response.
         return Ok(Response::new()
-            .add_message(bank_msg)
-            // This attribute should be present in the reply events.
-            .add_attribute("token_out_amount", amount));
+            .add_attribute("failed_pool_route", format!("{:?}", pool_ids))
+            .add_submessage(generate_swap_submsg(&swap_msg_reply_state)));
+    }
+
+    let reason = swap_msg_reply_state.failures.join("; ");
+    if swap_msg_reply_state.best_effort {
+        return refund_failed_swap(deps, env, swap_msg_reply_state, reason);
+    }
+
+    Err(ContractError::FailedSwap { reason })
+}
+
+// route_pool_ids returns the ids of the pools along the route.
+fn route_pool_ids(route: &[SwapAmountInRoute]) -> Vec<u64> {
+    route.iter().map(|route_part| route_part.pool_id).collect()
+}
+
+// refund_failed_swap sends the input of a failed best-effort swap back to
+// the original sender, releases the rate limit capacity the swap consumed
+// and reports the failure in a `swap_failed` event.
+fn refund_failed_swap(
+    deps: DepsMut,
+    env: Env,
+    swap_msg_reply_state: SwapMsgReplyState,
+    reason: String,
+) -> Result<Response, ContractError> {
+    let token_in = swap_msg_reply_state.swap_msg.token_in.unwrap_or_default();
+    let input_coin = coin(Uint128::from_str(&token_in.amount)?.u128(), token_in.denom);
+    release_rate_limit(
+        deps,
+        &swap_msg_reply_state.original_sender,
+        &input_coin,
+        env.block.time,
+    )?;
+
+    let bank_msg = BankMsg::Send {
+        to_address: swap_msg_reply_state.original_sender.to_string(),
+        amount: vec![input_coin.clone()],
+    };
+
+    let failed_event = Event::new("swap_failed")
+        .add_attribute("sender", swap_msg_reply_state.original_sender)
+        .add_attribute("refund", input_coin.to_string())
+        .add_attribute("reason", reason);
+
+    Ok(Response::new()
+        .add_message(bank_msg)
+        .add_event(failed_event))
+}
+
+// handle_step_swap_reply deserializes the response of a single hop of a
+// step-wise swap from Osmosis chain and reports the hop output in a `swap_hop` event.
+// If there are hops left, swap the hop output through the next hop.
+// Otherwise, settle the swap.
+// Returns contract error if the hop failed.
+pub fn handle_step_swap_reply(
+    deps: DepsMut,
+    env: Env,
+    msg: Reply,
+    swap_msg_reply_state: SwapMsgReplyState,
+) -> Result<Response, ContractError> {
+    if let SubMsgResult::Ok(SubMsgResponse { data: Some(b), .. }) = msg.result {
+        // Unwrap and deserialize message response.
+        let res: MsgSwapExactAmountInResponse = b.try_into().map_err(ContractError::Std)?;
+
+        // Retrieve swapped amount of this hop.
+        let amount = Uint128::from_str(&res.token_out_amount)?;
+
+        // Step-wise state is always saved along with step swap messages.
+        let step_wise = swap_msg_reply_state.step_wise.clone().unwrap();
+        let hop_route = &step_wise.route[step_wise.hop];
+        let hop_output = coin(amount.u128(), &hop_route.token_out_denom);
+
+        let token_in = swap_msg_reply_state
+            .swap_msg
+            .token_in
+            .clone()
+            .unwrap_or_default();
+        let hop_event = Event::new("swap_hop")
+            .add_attribute("hop", step_wise.hop.to_string())
+            .add_attribute("pool_id", hop_route.pool_id.to_string())
+            .add_attribute("token_in", format!("{}{}", token_in.amount, token_in.denom))
+            .add_attribute("token_out", hop_output.to_string());
+
+        // Swap the output of this hop through the next hop.
+        if step_wise.hop + 1 < step_wise.route.len() {
+            let step_wise = StepWiseState {
+                hop: step_wise.hop + 1,
+                ..step_wise
+            };
+            let swap_msg = generate_hop_swap_msg(
+                deps.as_ref(),
+                env.contract.address,
+                &step_wise,
+                hop_output,
+                env.block.time,
+            )?;
+
+            // save intermediate state for the reply of the next hop
+            SWAP_REPLY_STATES.save(
+                deps.storage,
+                STEP_SWAP_REPLY_ID,
+                &SwapMsgReplyState {
+                    swap_msg: swap_msg.clone(),
+                    step_wise: Some(step_wise),
+                    ..swap_msg_reply_state
+                },
+            )?;
+
+            return Ok(Response::new()
+                .add_event(hop_event)
+                .add_submessage(SubMsg::reply_on_success(swap_msg, STEP_SWAP_REPLY_ID)));
+        }
+
+        return Ok(settle_swap(
+            deps,
+            env,
+            swap_msg_reply_state.original_sender,
+            step_wise.input_coin,
+            amount,
+            &step_wise.route,
+            swap_msg_reply_state.swap_type,
+        )?
+        .add_event(hop_event));
     }
 
     Err(ContractError::FailedSwap {
@@ -137,13 +559,60 @@ pub fn handle_swap_reply(
     })
 }
 
+// settle_swap sends the swapped token to the original user who initiated
+// the swap, updates the pair statistics and price accumulator and records
+// the swap in the swap history.
+fn settle_swap(
+    deps: DepsMut,
+    env: Env,
+    original_sender: Addr,
+    input_coin: Coin,
+    amount: Uint128,
+    route: &[SwapAmountInRoute],
+    swap_type: SwapType,
+) -> Result<Response, ContractError> {
+    // Retrieve swapped denom from the route.
+    let send_denom = &route.last().unwrap().token_out_denom;
+
+    // Send the swapped token from contract to the original
+    // user who initiated the swap.
+    let bank_msg = BankMsg::Send {
+        to_address: original_sender.to_string(),
+        amount: coins(amount.u128(), send_denom),
+    };
+
+    let output_coin = coin(amount.u128(), send_denom);
+
+    update_pair_stats(deps.storage, &input_coin, &output_coin, env.block.time)?;
+    update_price_accumulator(deps.storage, &input_coin, &output_coin, env.block.time)?;
+
+    record_swap_history(
+        deps.storage,
+        SwapRecord {
+            sender: original_sender,
+            input_coin,
+            output_coin,
+            pool_ids: route.iter().map(|route_part| route_part.pool_id).collect(),
+            block_height: env.block.height,
+            time: env.block.time,
+            swap_type,
+        },
+    )?;
+
+    // Success response.
+    Ok(Response::new()
+        .add_message(bank_msg)
+        // This attribute should be present in the reply events.
+        .add_attribute("token_out_amount", amount))
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::msg::GetOwnerResponse;
+    use crate::msg::{ConfigUpdate, GetOwnerResponse};
 
     use super::*;
     use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MOCK_CONTRACT_ADDR};
-    use cosmwasm_std::{coins, from_binary};
+    use cosmwasm_std::{coins, from_json};
 
     #[test]
     fn instantiate_works() {
@@ -151,6 +620,9 @@ mod tests {
 
         let msg = InstantiateMsg {
             owner: String::from(MOCK_CONTRACT_ADDR),
+            config: ConfigUpdate::default(),
+            routes: vec![],
+            pair_configs: vec![],
         };
         let info = mock_info("creator", &coins(1000, "earth"));
 
@@ -159,7 +631,7 @@ mod tests {
 
         // make sure that the owner was set correctly.
         let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOwner {}).unwrap();
-        let value: GetOwnerResponse = from_binary(&res).unwrap();
+        let value: GetOwnerResponse = from_json(&res).unwrap();
         assert_eq!(MOCK_CONTRACT_ADDR, value.owner);
     }
 }
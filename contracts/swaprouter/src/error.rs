@@ -1,4 +1,6 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{Decimal, StdError};
+
+use crate::msg::Deadline;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -15,9 +17,51 @@ pub enum ContractError {
     #[error("Insufficient Funds")]
     InsufficientFunds {},
 
+    #[error("Deadline Exceeded: {deadline:?}")]
+    DeadlineExceeded { deadline: Deadline },
+
+    #[error("Invalid Slippage Percentage: {percentage}% is more than 100%")]
+    InvalidSlippagePercentage { percentage: Decimal },
+
     #[error("Failed Swap: {reason:?}")]
     FailedSwap { reason: String },
 
+    #[error("Spot price {spot_price} deviates from the {price_source} price {reference_price} by more than {max_deviation_percentage}%")]
+    SpotPriceDeviation {
+        spot_price: Decimal,
+        reference_price: Decimal,
+        price_source: String,
+        max_deviation_percentage: Decimal,
+    },
+
+    #[error("Short window TWAP {short_twap} diverges from long window TWAP {long_twap} by more than {max_divergence_percentage}%")]
+    TwapWindowDivergence {
+        short_twap: Decimal,
+        long_twap: Decimal,
+        max_divergence_percentage: Decimal,
+    },
+
+    #[error("Route Timelocked: {reason:?}")]
+    RouteTimelocked { reason: String },
+
+    #[error("Invalid Denom Alias: {reason:?}")]
+    InvalidDenomAlias { reason: String },
+
+    #[error("Denom Not Allowed: {denom:?}")]
+    DenomNotAllowed { denom: String },
+
+    #[error("Invalid Rate Limit: {reason:?}")]
+    InvalidRateLimit { reason: String },
+
+    #[error("Rate Limit Exceeded: {reason:?}")]
+    RateLimitExceeded { reason: String },
+
+    #[error("Invalid Pair Config: {reason:?}")]
+    InvalidPairConfig { reason: String },
+
+    #[error("Pair Limit Exceeded: {reason:?}")]
+    PairLimitExceeded { reason: String },
+
     #[error("Custom Error val: {val:?}")]
     CustomError { val: String },
     // Add any other custom errors you like here.
@@ -1,20 +1,45 @@
-use cosmwasm_std::{coin, has_coins, Coin, DepsMut, Env, MessageInfo, Response, SubMsg};
-use osmosis_std::types::osmosis::gamm::v1beta1::SwapAmountInRoute;
+use cosmwasm_std::{
+    coin, has_coins, Addr, BlockInfo, Coin, Deps, DepsMut, Empty, Env, Event, MessageInfo, Order,
+    Response, StdResult, SubMsg, Timestamp,
+};
+use cw_storage_plus::Bound;
+use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountInRoute;
 
-use crate::contract::SWAP_REPLY_ID;
+use crate::contract::{MAX_DENOM_EXPONENT, STEP_SWAP_REPLY_ID, SWAP_REPLY_ID};
 use crate::helpers::{
-    calculate_min_output_from_twap, generate_swap_msg, validate_is_contract_owner,
-    validate_pool_route,
+    calculate_min_output_from_twap, consume_rate_limit, expand_route_segments,
+    generate_hop_swap_msg, generate_swap_msg, generate_swap_submsg, is_used_denom,
+    link_route_composition, pair_config_only_restricts, pool_ids, remove_route, resolve_denom,
+    resolve_pool_route, resolve_route_segments, reverse_route, route_rejection_reason, save_route,
+    to_swap_route, update_dependent_routes, validate_and_save_routes, validate_is_contract_owner,
+    validate_min_output_floor, validate_pair_config, validate_pair_limits, validate_pool_route,
+    validate_rate_limit, validate_route_composition, validate_route_denoms,
+    validate_route_expiration, validate_slippage_percentage,
+};
+use crate::msg::{ConfigUpdate, Deadline, DenomList, ExecutionMode, RouteEntry, SwapType};
+use crate::query::{DEFAULT_LIMIT, MAX_LIMIT};
+use crate::state::{
+    DenomAlias, PairConfig, ProposedRoute, RateLimit, RouteSegment, StepWiseState,
+    SwapMsgReplyState, ALLOWED_DENOMS, BLOCKED_DENOMS, CONFIG, DENOM_ALIASES, DENOM_SYMBOLS,
+    FALLBACK_ROUTES, PAIR_CONFIGS, POOL_ROUTES, PROPOSED_ROUTES, RATE_LIMITS, ROUTE_EXPIRATIONS,
+    ROUTING_TABLE, SUSPENDED_ROUTES, SWAP_REPLY_STATES,
 };
-use crate::msg::SwapType;
-use crate::state::{SwapMsgReplyState, ROUTING_TABLE, SWAP_REPLY_STATES};
 use crate::ContractError;
 
 // set_route sets route for swaps. Only contract owner may execute this message.
+// Denoms may be given as the symbols of denom aliases.
+// Segments referencing the stored route of another pair are expanded into its hops,
+// and the route is updated along with the referenced routes when they change.
+// If bidirectional, the reverse route through the same pools is set as well.
+// If propagate, the routes composed of the changed routes are re-expanded.
 // Returns response with attributes on success.
 // Errors if:
 // - executed by anyone other than the owner
-// - invalid pool route given
+// - the route timelock is configured, routes must be proposed with `ProposeRoute` instead
+// - a referenced route is missing or depends on the route being set
+// - invalid pool route given, or its reverse is invalid if bidirectional
+// - the route expires before now
+// - other routes are composed of the changed routes and propagate is false
 //
 // Example 1 (one-hop):
 // OSMO -> ATOM
@@ -27,15 +52,203 @@ use crate::ContractError;
 // input: OSMO
 // OUTPUT: ATOM
 // ROUTE = [ { pool_id: 1, token_out_denom: ATOM }, { pool_id: 2, token_out_denom: STAKE } ]
+//
+// Example 3 (composed):
+// OSMO -> ATOM -> STAKE
+// input: OSMO
+// OUTPUT: STAKE
+// ROUTE = [ { input_denom: OSMO, output_denom: ATOM }, { pool_id: 2, token_out_denom: STAKE } ]
+#[allow(clippy::too_many_arguments)]
 pub fn set_route(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    input_denom: String,
+    output_denom: String,
+    pool_route: Vec<RouteSegment>,
+    bidirectional: bool,
+    expires_at: Option<Timestamp>,
+    propagate: bool,
+) -> Result<Response, ContractError> {
+    validate_is_contract_owner(deps.as_ref(), info.sender.clone())?;
+    validate_no_route_timelock(deps.as_ref(), "routes must be set with ProposeRoute")?;
+
+    let input_denom = resolve_denom(deps.storage, &input_denom)?;
+    let output_denom = resolve_denom(deps.storage, &output_denom)?;
+    let pool_route = resolve_route_segments(deps.storage, pool_route)?;
+    validate_route_composition(deps.storage, &input_denom, &output_denom, &pool_route)?;
+    let segments = pool_route;
+    let pool_route = expand_route_segments(deps.storage, &segments, env.block.time)?;
+
+    let response = apply_route(
+        deps.branch(),
+        &env.block,
+        &info.sender,
+        RouteEntry {
+            input_denom: input_denom.clone(),
+            output_denom: output_denom.clone(),
+            pool_route,
+            expires_at,
+        },
+        bidirectional,
+        propagate,
+        Response::new().add_attribute("action", "set_route"),
+    )?;
+    link_route_composition(deps.storage, &input_denom, &output_denom, segments)?;
+
+    Ok(response)
+}
+
+// apply_route validates and saves the route, and its reverse if bidirectional,
+// adding the previous pool ids of the replaced routes to response.
+// If propagate, the routes composed of the saved routes are re-expanded,
+// with a `route_propagated` event per updated route.
+// Errors if:
+// - invalid pool route given, or its reverse is invalid if bidirectional
+// - the route expires before now
+// - other routes are composed of the saved routes and propagate is false
+fn apply_route(
+    mut deps: DepsMut,
+    block: &BlockInfo,
+    changed_by: &Addr,
+    route: RouteEntry,
+    bidirectional: bool,
+    propagate: bool,
+    response: Response,
+) -> Result<Response, ContractError> {
+    let RouteEntry {
+        input_denom,
+        output_denom,
+        pool_route,
+        expires_at,
+    } = route;
+    validate_route_expiration(expires_at, block.time)?;
+
+    let reverse_pool_route = reverse_route(&input_denom, &pool_route);
+    let pool_route = validate_pool_route(
+        deps.as_ref(),
+        input_denom.clone(),
+        output_denom.clone(),
+        pool_route,
+    )?;
+
+    let mut response = response.add_attribute("bidirectional", bidirectional.to_string());
+    let mut updated = vec![];
+
+    if bidirectional {
+        let reverse_pool_route = validate_pool_route(
+            deps.as_ref(),
+            output_denom.clone(),
+            input_denom.clone(),
+            reverse_pool_route,
+        )?;
+        let previous_route = save_route(
+            deps.storage,
+            &output_denom,
+            &input_denom,
+            reverse_pool_route,
+            expires_at,
+            changed_by,
+            block,
+        )?;
+        response = response.add_attribute("previous_reverse_pool_ids", pool_ids(&previous_route));
+        updated = update_dependent_routes(
+            deps.branch(),
+            &output_denom,
+            &input_denom,
+            propagate,
+            changed_by,
+            block,
+        )?;
+    }
+
+    let previous_route = save_route(
+        deps.storage,
+        &input_denom,
+        &output_denom,
+        pool_route,
+        expires_at,
+        changed_by,
+        block,
+    )?;
+    for dependent in update_dependent_routes(
+        deps,
+        &input_denom,
+        &output_denom,
+        propagate,
+        changed_by,
+        block,
+    )? {
+        if !updated.contains(&dependent) {
+            updated.push(dependent);
+        }
+    }
+
+    for (dependent_input, dependent_output) in updated {
+        response = response.add_event(
+            Event::new("route_propagated")
+                .add_attribute("input_denom", dependent_input)
+                .add_attribute("output_denom", dependent_output),
+        );
+    }
+
+    Ok(response.add_attribute("previous_pool_ids", pool_ids(&previous_route)))
+}
+
+// validate_no_route_timelock returns success if routing settings may be changed directly,
+// error with the given reason otherwise.
+// Errors if:
+// - the route timelock is configured
+fn validate_no_route_timelock(deps: Deps, reason: &str) -> Result<(), ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.route_timelock_seconds > 0 {
+        return Err(ContractError::RouteTimelocked {
+            reason: reason.to_string(),
+        });
+    }
+    Ok(())
+}
+
+// propose_route proposes a route for swaps, executable with `ExecuteProposedRoute`
+// once the route timelock has elapsed. A previous proposal for the pair is replaced.
+// If propagate, the routes composed of the route are re-expanded when it is executed.
+// Denoms may be given as the symbols of denom aliases.
+// Only contract owner may execute this message.
+// Returns response with attributes on success.
+// Errors if:
+// - executed by anyone other than the owner
+// - invalid pool route given, or its reverse is invalid if bidirectional
+// - the route expires before it can be executed
+#[allow(clippy::too_many_arguments)]
+pub fn propose_route(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     input_denom: String,
     output_denom: String,
     pool_route: Vec<SwapAmountInRoute>,
+    bidirectional: bool,
+    expires_at: Option<Timestamp>,
+    propagate: bool,
 ) -> Result<Response, ContractError> {
-    validate_is_contract_owner(deps.as_ref(), info.sender)?;
+    validate_is_contract_owner(deps.as_ref(), info.sender.clone())?;
+
+    let input_denom = resolve_denom(deps.storage, &input_denom)?;
+    let output_denom = resolve_denom(deps.storage, &output_denom)?;
+    let pool_route = resolve_pool_route(deps.storage, pool_route)?;
+    let config = CONFIG.load(deps.storage)?;
+    let executable_after = env.block.time.plus_seconds(config.route_timelock_seconds);
+    validate_route_expiration(expires_at, executable_after)?;
 
+    // the route is validated again on execution, pools may change in the meantime.
+    if bidirectional {
+        validate_pool_route(
+            deps.as_ref(),
+            output_denom.clone(),
+            input_denom.clone(),
+            reverse_route(&input_denom, &pool_route),
+        )?;
+    }
     validate_pool_route(
         deps.as_ref(),
         input_denom.clone(),
@@ -43,31 +256,180 @@ pub fn set_route(
         pool_route.clone(),
     )?;
 
-    ROUTING_TABLE.save(deps.storage, (&input_denom, &output_denom), &pool_route)?;
+    let proposal = ProposedRoute {
+        proposed_by: info.sender,
+        pool_route,
+        bidirectional,
+        expires_at,
+        propagate,
+        proposed_at: env.block.time,
+    };
+    PROPOSED_ROUTES.save(deps.storage, (&input_denom, &output_denom), &proposal)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "propose_route")
+        .add_attribute("input_denom", input_denom)
+        .add_attribute("output_denom", output_denom)
+        .add_attribute("executable_after", executable_after.seconds().to_string()))
+}
+
+// execute_proposed_route sets the route proposed for the pair with `ProposeRoute`.
+// Denoms may be given as the symbols of denom aliases.
+// Only contract owner may execute this message.
+// Returns response with attributes on success.
+// Errors if:
+// - executed by anyone other than the owner
+// - no route is proposed for the pair
+// - the route timelock has not elapsed since the proposal, the timelock being
+//   the current one so that raising it also delays pending proposals
+// - the proposed pool route is no longer valid or has expired
+// - other routes are composed of the changed routes and the proposal does not propagate
+pub fn execute_proposed_route(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    input_denom: String,
+    output_denom: String,
+) -> Result<Response, ContractError> {
+    validate_is_contract_owner(deps.as_ref(), info.sender.clone())?;
+
+    let input_denom = resolve_denom(deps.storage, &input_denom)?;
+    let output_denom = resolve_denom(deps.storage, &output_denom)?;
+    let proposal = PROPOSED_ROUTES.load(deps.storage, (&input_denom, &output_denom))?;
+    let config = CONFIG.load(deps.storage)?;
+    let executable_after = proposal
+        .proposed_at
+        .plus_seconds(config.route_timelock_seconds);
+    if env.block.time < executable_after {
+        return Err(ContractError::RouteTimelocked {
+            reason: format!(
+                "proposed route is executable after {}",
+                executable_after.seconds()
+            ),
+        });
+    }
+    PROPOSED_ROUTES.remove(deps.storage, (&input_denom, &output_denom));
+
+    apply_route(
+        deps,
+        &env.block,
+        &info.sender,
+        RouteEntry {
+            input_denom,
+            output_denom,
+            pool_route: proposal.pool_route,
+            expires_at: proposal.expires_at,
+        },
+        proposal.bidirectional,
+        proposal.propagate,
+        Response::new().add_attribute("action", "execute_proposed_route"),
+    )
+}
+
+// cancel_proposed_route withdraws the route proposed for the pair with `ProposeRoute`.
+// Denoms may be given as the symbols of denom aliases.
+// Only contract owner may execute this message.
+// Returns response with attributes on success.
+// Errors if:
+// - executed by anyone other than the owner
+// - no route is proposed for the pair
+pub fn cancel_proposed_route(
+    deps: DepsMut,
+    info: MessageInfo,
+    input_denom: String,
+    output_denom: String,
+) -> Result<Response, ContractError> {
+    validate_is_contract_owner(deps.as_ref(), info.sender)?;
+
+    let input_denom = resolve_denom(deps.storage, &input_denom)?;
+    let output_denom = resolve_denom(deps.storage, &output_denom)?;
+    PROPOSED_ROUTES.load(deps.storage, (&input_denom, &output_denom))?;
+    PROPOSED_ROUTES.remove(deps.storage, (&input_denom, &output_denom));
+
+    Ok(Response::new()
+        .add_attribute("action", "cancel_proposed_route")
+        .add_attribute("input_denom", input_denom)
+        .add_attribute("output_denom", output_denom))
+}
+
+// set_routes sets routes for swaps in bulk. Only contract owner may execute this message.
+// Every route is validated before any of them is saved.
+// Returns response with attributes on success.
+// Errors if:
+// - executed by anyone other than the owner
+// - the route timelock is configured
+// - any of the routes is an invalid pool route
+pub fn set_routes(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    routes: Vec<RouteEntry>,
+) -> Result<Response, ContractError> {
+    validate_is_contract_owner(deps.as_ref(), info.sender.clone())?;
+    validate_no_route_timelock(deps.as_ref(), "routes must be set with ProposeRoute")?;
+
+    let count = validate_and_save_routes(deps, routes, &info.sender, &env.block)?;
 
-    Ok(Response::new().add_attribute("action", "set_route"))
+    Ok(Response::new()
+        .add_attribute("action", "set_routes")
+        .add_attribute("routes", count.to_string()))
 }
 
 // swap initiates an Osmosis swap message of the input_coin to at least
 // minimum_output_token of another coin. Wraps the message into
 // CosmWasm swap message to receive reply from the respective entrypoint.
+// Denoms may be given as the symbols of denom aliases.
+// If the swap fails, the reply retries it through the fallback routes of the pair.
+// In step-wise execution mode only the first hop is swapped here, the reply
+// of every hop swaps through the next one.
 // Returns error if:
+// - the deadline has passed.
 // - funds sent in by the initiator do no match the input_coin.
+// - the max slippage percentage or the hop max slippage percentage is more than 100.
+// - any denom along the route is blocklisted, or not allowlisted if the allowlist is enabled.
+// - the swap exceeds the slippage or input limits of the pair, including a minimum output
+//   below the one allowed by the maximum slippage percentage of the pair.
+// - the input exceeds the remaining rate limit capacity of its denom or of the sender.
 // - fails to generate the message.
+#[allow(clippy::too_many_arguments)]
 pub fn swap(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     input_coin: Coin,
     output_denom: String,
     swap_type: SwapType,
+    deadline: Option<Deadline>,
+    execution_mode: Option<ExecutionMode>,
 ) -> Result<Response, ContractError> {
+    if let Some(deadline) = deadline {
+        if deadline.is_passed(&env.block) {
+            return Err(ContractError::DeadlineExceeded { deadline });
+        }
+    }
+
+    if let SwapType::MaxSlippagePercentage(percentage) = swap_type {
+        validate_slippage_percentage(percentage)?;
+    }
+    if let Some(ExecutionMode::StepWise {
+        hop_max_slippage_percentage,
+    }) = execution_mode
+    {
+        validate_slippage_percentage(hop_max_slippage_percentage)?;
+    }
+
+    let input_coin = Coin {
+        denom: resolve_denom(deps.storage, &input_coin.denom)?,
+        amount: input_coin.amount,
+    };
+    let output_denom = resolve_denom(deps.storage, &output_denom)?;
+
     if !has_coins(&info.funds, &input_coin) {
         return Err(ContractError::InsufficientFunds {});
     }
 
     // get minimum output coin from swap type.
-    let minimum_output_token = match swap_type {
+    let minimum_output_token = match swap_type.clone() {
         SwapType::MaxSlippagePercentage(percentage) => calculate_min_output_from_twap(
             deps.as_ref(),
             input_coin.clone(),
@@ -76,30 +438,641 @@ pub fn swap(
             percentage,
         )?,
         SwapType::MinOutputAmount(minimum_output_amount) => {
-            coin(minimum_output_amount.u128(), output_denom)
+            let minimum_output_token = coin(minimum_output_amount.u128(), output_denom);
+            validate_min_output_floor(
+                deps.as_ref(),
+                &input_coin,
+                &minimum_output_token,
+                env.block.time,
+            )?;
+            minimum_output_token
         }
     };
 
     // generate the swap message using osmosis-rust (osmosis_std).
     let swap_msg = generate_swap_msg(
         deps.as_ref(),
-        env.contract.address,
-        input_coin,
-        minimum_output_token,
+        env.contract.address.clone(),
+        input_coin.clone(),
+        minimum_output_token.clone(),
+        env.block.time,
     )?;
-
-    // save intermediate state for reply
-    SWAP_REPLY_STATES.save(
+    validate_route_denoms(
         deps.storage,
-        SWAP_REPLY_ID,
-        &SwapMsgReplyState {
-            original_sender: info.sender,
-            swap_msg: swap_msg.clone(),
-        },
+        &input_coin.denom,
+        &minimum_output_token.denom,
+        &swap_msg.routes,
+    )?;
+    validate_pair_limits(
+        deps.as_ref(),
+        &input_coin,
+        &minimum_output_token.denom,
+        &swap_type,
+        &swap_msg.routes,
     )?;
+    consume_rate_limit(deps.branch(), &info.sender, &input_coin, env.block.time)?;
+
+    if let Some(ExecutionMode::StepWise {
+        hop_max_slippage_percentage,
+    }) = execution_mode
+    {
+        let step_wise = StepWiseState {
+            route: swap_msg.routes,
+            hop: 0,
+            input_coin: input_coin.clone(),
+            min_output: minimum_output_token,
+            hop_max_slippage_percentage,
+        };
+        let hop_msg = generate_hop_swap_msg(
+            deps.as_ref(),
+            env.contract.address,
+            &step_wise,
+            input_coin,
+            env.block.time,
+        )?;
+
+        // save intermediate state for reply
+        SWAP_REPLY_STATES.save(
+            deps.storage,
+            STEP_SWAP_REPLY_ID,
+            &SwapMsgReplyState {
+                original_sender: info.sender,
+                swap_msg: hop_msg.clone(),
+                swap_type,
+                step_wise: Some(step_wise),
+                best_effort: false,
+                fallback_routes: vec![],
+                failures: vec![],
+            },
+        )?;
+
+        return Ok(Response::new()
+            .add_attribute("action", "swap")
+            // add sub message for the first hop. See reply entrypoint for the following hops.
+            .add_submessage(SubMsg::reply_on_success(hop_msg, STEP_SWAP_REPLY_ID)));
+    }
+
+    let best_effort = execution_mode == Some(ExecutionMode::BestEffort);
+    let fallback_routes = FALLBACK_ROUTES
+        .may_load(
+            deps.storage,
+            (&input_coin.denom, &minimum_output_token.denom),
+        )?
+        .unwrap_or_default()
+        .into_iter()
+        .map(to_swap_route)
+        .collect();
+
+    // save intermediate state for reply
+    let swap_msg_reply_state = SwapMsgReplyState {
+        original_sender: info.sender,
+        swap_msg,
+        swap_type,
+        step_wise: None,
+        best_effort,
+        fallback_routes,
+        failures: vec![],
+    };
+    SWAP_REPLY_STATES.save(deps.storage, SWAP_REPLY_ID, &swap_msg_reply_state)?;
+    let swap_submsg = generate_swap_submsg(&swap_msg_reply_state);
 
     Ok(Response::new()
         .add_attribute("action", "swap")
-        // add sub message with reply on success. See reply entrypoint for the continuation of the flow.
-        .add_submessage(SubMsg::reply_on_success(swap_msg, SWAP_REPLY_ID)))
+        // add sub message with reply. See reply entrypoint for the continuation of the flow.
+        .add_submessage(swap_submsg))
+}
+
+// prune_invalid_routes re-validates the stored routes ordered by (input_denom, output_denom),
+// starting after the given pair, suspends the ones that fail validation
+// and restores the suspended ones that pass it again.
+// A route that could not be validated, such as on a failed pool query, is suspended as well.
+// Routes composed of a suspended route keep their own hops and are validated on their own.
+// Anyone may execute this message.
+// Returns response with a `route_pruned` event per newly suspended route
+// and a `route_restored` event per restored route.
+pub fn prune_invalid_routes(
+    deps: DepsMut,
+    start_after: Option<(String, String)>,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_ref().map(|(input_denom, output_denom)| {
+        Bound::exclusive((input_denom.as_str(), output_denom.as_str()))
+    });
+
+    let routes = ROUTING_TABLE
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut response = Response::new().add_attribute("action", "prune_invalid_routes");
+    for ((input_denom, output_denom), route) in routes {
+        let reason = route_rejection_reason(
+            deps.as_ref(),
+            input_denom.clone(),
+            output_denom.clone(),
+            to_swap_route(route),
+        )
+        .unwrap_or_else(|err| Some(err.to_string()));
+        let suspended = SUSPENDED_ROUTES.has(deps.storage, (&input_denom, &output_denom));
+        match reason {
+            Some(reason) => {
+                SUSPENDED_ROUTES.save(deps.storage, (&input_denom, &output_denom), &reason)?;
+                if !suspended {
+                    response = response.add_event(
+                        Event::new("route_pruned")
+                            .add_attribute("input_denom", input_denom)
+                            .add_attribute("output_denom", output_denom)
+                            .add_attribute("reason", reason),
+                    );
+                }
+            }
+            None if suspended => {
+                SUSPENDED_ROUTES.remove(deps.storage, (&input_denom, &output_denom));
+                response = response.add_event(
+                    Event::new("route_restored")
+                        .add_attribute("input_denom", input_denom)
+                        .add_attribute("output_denom", output_denom),
+                );
+            }
+            None => {}
+        }
+    }
+
+    Ok(response)
+}
+
+// restore_route lifts the suspension of the route from input_denom to output_denom,
+// or the denoms of their aliases, without re-validating it.
+// Only contract owner may execute this message.
+// Returns response with a `route_restored` event.
+// Errors if:
+// - executed by anyone other than the owner
+// - the route is not suspended
+pub fn restore_route(
+    deps: DepsMut,
+    info: MessageInfo,
+    input_denom: String,
+    output_denom: String,
+) -> Result<Response, ContractError> {
+    validate_is_contract_owner(deps.as_ref(), info.sender)?;
+
+    let input_denom = resolve_denom(deps.storage, &input_denom)?;
+    let output_denom = resolve_denom(deps.storage, &output_denom)?;
+    if !SUSPENDED_ROUTES.has(deps.storage, (&input_denom, &output_denom)) {
+        return Err(ContractError::InvalidPoolRoute {
+            reason: format!("route from {input_denom} to {output_denom} is not suspended"),
+        });
+    }
+    SUSPENDED_ROUTES.remove(deps.storage, (&input_denom, &output_denom));
+
+    Ok(Response::new()
+        .add_attribute("action", "restore_route")
+        .add_event(
+            Event::new("route_restored")
+                .add_attribute("input_denom", input_denom)
+                .add_attribute("output_denom", output_denom),
+        ))
+}
+
+// add_dependent_route_removed_events adds a `dependent_route_removed` event to response
+// per route removed along with a route it is composed of.
+fn add_dependent_route_removed_events(
+    response: Response,
+    removed: Vec<(String, String)>,
+) -> Response {
+    response.add_events(removed.into_iter().map(|(input_denom, output_denom)| {
+        Event::new("dependent_route_removed")
+            .add_attribute("input_denom", input_denom)
+            .add_attribute("output_denom", output_denom)
+    }))
+}
+
+// remove_expired_routes removes the expired routes, scanning the routes with an expiration
+// ordered by (input_denom, output_denom), starting after the given pair.
+// Anyone may execute this message.
+// Returns response with a `route_expired` event per removed route
+// and a `dependent_route_removed` event per route composed of a removed route.
+pub fn remove_expired_routes(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    start_after: Option<(String, String)>,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_ref().map(|(input_denom, output_denom)| {
+        Bound::exclusive((input_denom.as_str(), output_denom.as_str()))
+    });
+
+    let expirations = ROUTE_EXPIRATIONS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut response = Response::new().add_attribute("action", "remove_expired_routes");
+    for ((input_denom, output_denom), expires_at) in expirations {
+        if expires_at <= env.block.time
+            && ROUTING_TABLE.has(deps.storage, (&input_denom, &output_denom))
+        {
+            let removed = remove_route(
+                deps.storage,
+                &input_denom,
+                &output_denom,
+                &info.sender,
+                &env.block,
+            )?;
+            response = add_dependent_route_removed_events(response, removed).add_event(
+                Event::new("route_expired")
+                    .add_attribute("input_denom", input_denom)
+                    .add_attribute("output_denom", output_denom)
+                    .add_attribute("expires_at", expires_at.seconds().to_string()),
+            );
+        }
+    }
+
+    Ok(response)
+}
+
+// disable_pool removes every route, fallback route and proposed route
+// that swaps through the given pool. Only contract owner may execute this message.
+// Returns response with a `route_disabled` event per removed route,
+// a `dependent_route_removed` event per route composed of a removed route,
+// a `fallback_route_disabled` event per removed fallback route
+// and a `proposed_route_cancelled` event per cancelled proposal.
+// Errors if:
+// - executed by anyone other than the owner
+pub fn disable_pool(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool_id: u64,
+) -> Result<Response, ContractError> {
+    validate_is_contract_owner(deps.as_ref(), info.sender.clone())?;
+
+    let pairs = POOL_ROUTES
+        .sub_prefix(pool_id)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut response = Response::new()
+        .add_attribute("action", "disable_pool")
+        .add_attribute("pool_id", pool_id.to_string());
+    for (input_denom, output_denom) in pairs {
+        if !ROUTING_TABLE.has(deps.storage, (&input_denom, &output_denom)) {
+            continue;
+        }
+        let removed = remove_route(
+            deps.storage,
+            &input_denom,
+            &output_denom,
+            &info.sender,
+            &env.block,
+        )?;
+        response = add_dependent_route_removed_events(response, removed).add_event(
+            Event::new("route_disabled")
+                .add_attribute("input_denom", input_denom)
+                .add_attribute("output_denom", output_denom),
+        );
+    }
+
+    // fallback routes and proposals are not indexed by pool, so all of them are scanned.
+    let fallback_routes = FALLBACK_ROUTES
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for ((input_denom, output_denom), routes) in fallback_routes {
+        let (disabled, kept): (Vec<_>, Vec<_>) = routes
+            .into_iter()
+            .partition(|route| route.iter().any(|hop| hop.pool_id == pool_id));
+        if disabled.is_empty() {
+            continue;
+        }
+        if kept.is_empty() {
+            FALLBACK_ROUTES.remove(deps.storage, (&input_denom, &output_denom));
+        } else {
+            FALLBACK_ROUTES.save(deps.storage, (&input_denom, &output_denom), &kept)?;
+        }
+        for route in disabled {
+            response = response.add_event(
+                Event::new("fallback_route_disabled")
+                    .add_attribute("input_denom", &input_denom)
+                    .add_attribute("output_denom", &output_denom)
+                    .add_attribute("pool_ids", pool_ids(&route)),
+            );
+        }
+    }
+
+    let proposals = PROPOSED_ROUTES
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for ((input_denom, output_denom), proposal) in proposals {
+        if proposal.pool_route.iter().any(|hop| hop.pool_id == pool_id) {
+            PROPOSED_ROUTES.remove(deps.storage, (&input_denom, &output_denom));
+            response = response.add_event(
+                Event::new("proposed_route_cancelled")
+                    .add_attribute("input_denom", input_denom)
+                    .add_attribute("output_denom", output_denom),
+            );
+        }
+    }
+
+    Ok(response)
+}
+
+// set_fallback_routes sets the ordered routes tried when the route of the pair fails.
+// An empty list removes the fallback routes. Only contract owner may execute this message.
+// Denoms may be given as the symbols of denom aliases.
+// Returns response with attributes on success.
+// Errors if:
+// - executed by anyone other than the owner
+// - the route timelock is configured and a fallback route is not already set for the pair
+// - any of the fallback routes is an invalid pool route
+pub fn set_fallback_routes(
+    deps: DepsMut,
+    info: MessageInfo,
+    input_denom: String,
+    output_denom: String,
+    fallback_routes: Vec<Vec<SwapAmountInRoute>>,
+) -> Result<Response, ContractError> {
+    validate_is_contract_owner(deps.as_ref(), info.sender)?;
+
+    let input_denom = resolve_denom(deps.storage, &input_denom)?;
+    let output_denom = resolve_denom(deps.storage, &output_denom)?;
+    let fallback_routes = fallback_routes
+        .into_iter()
+        .map(|pool_route| {
+            validate_pool_route(
+                deps.as_ref(),
+                input_denom.clone(),
+                output_denom.clone(),
+                resolve_pool_route(deps.storage, pool_route)?,
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // removing or reordering the fallback routes does not add a route to swap through.
+    let current = FALLBACK_ROUTES
+        .may_load(deps.storage, (&input_denom, &output_denom))?
+        .unwrap_or_default();
+    if !fallback_routes.iter().all(|route| current.contains(route)) {
+        validate_no_route_timelock(
+            deps.as_ref(),
+            "fallback routes can only be removed while the route timelock is configured",
+        )?;
+    }
+
+    if fallback_routes.is_empty() {
+        FALLBACK_ROUTES.remove(deps.storage, (&input_denom, &output_denom));
+    } else {
+        FALLBACK_ROUTES.save(
+            deps.storage,
+            (&input_denom, &output_denom),
+            &fallback_routes,
+        )?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "set_fallback_routes")
+        .add_attribute("fallback_routes", fallback_routes.len().to_string()))
+}
+
+// set_rate_limit sets the swap volume limit of denom, or of the denom of its alias,
+// or removes it if not given. Only contract owner may execute this message.
+// Returns response with attributes on success.
+// Errors if:
+// - executed by anyone other than the owner
+// - the rate limit is invalid
+pub fn set_rate_limit(
+    deps: DepsMut,
+    info: MessageInfo,
+    denom: String,
+    rate_limit: Option<RateLimit>,
+) -> Result<Response, ContractError> {
+    validate_is_contract_owner(deps.as_ref(), info.sender)?;
+
+    let denom = resolve_denom(deps.storage, &denom)?;
+    match &rate_limit {
+        Some(rate_limit) => {
+            validate_rate_limit(deps.as_ref(), rate_limit)?;
+            RATE_LIMITS.save(deps.storage, &denom, rate_limit)?;
+        }
+        None => RATE_LIMITS.remove(deps.storage, &denom),
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "set_rate_limit")
+        .add_attribute("denom", denom)
+        .add_attribute("rate_limited", rate_limit.is_some().to_string()))
+}
+
+// update_denom_list adds and then removes denoms, or the symbols of their aliases,
+// from the allowlist or the blocklist. Only contract owner may execute this message.
+// Returns response with attributes on success.
+// Errors if:
+// - executed by anyone other than the owner
+pub fn update_denom_list(
+    deps: DepsMut,
+    info: MessageInfo,
+    list: DenomList,
+    add: Vec<String>,
+    remove: Vec<String>,
+) -> Result<Response, ContractError> {
+    validate_is_contract_owner(deps.as_ref(), info.sender)?;
+
+    let denoms = match list {
+        DenomList::Allowlist => ALLOWED_DENOMS,
+        DenomList::Blocklist => BLOCKED_DENOMS,
+    };
+    for denom in &add {
+        let denom = resolve_denom(deps.storage, denom)?;
+        denoms.save(deps.storage, &denom, &Empty {})?;
+    }
+    for denom in &remove {
+        let denom = resolve_denom(deps.storage, denom)?;
+        denoms.remove(deps.storage, &denom);
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_denom_list")
+        .add_attribute("added", add.join(","))
+        .add_attribute("removed", remove.join(",")))
+}
+
+// set_denom_alias sets the denom the symbol stands for, replacing the previous alias of the symbol.
+// Only contract owner may execute this message.
+// Returns response with attributes on success.
+// Errors if:
+// - executed by anyone other than the owner
+// - the route timelock is configured and the symbol is given another denom
+// - the symbol or denom is empty, or the denom already has another symbol
+// - the symbol is a denom, aliased or used in the routing settings, or the denom is a symbol
+// - the exponent is larger than 18
+pub fn set_denom_alias(
+    deps: DepsMut,
+    info: MessageInfo,
+    symbol: String,
+    alias: DenomAlias,
+) -> Result<Response, ContractError> {
+    validate_is_contract_owner(deps.as_ref(), info.sender)?;
+    // changing the display settings of an alias does not change the denom it stands for.
+    let previous = DENOM_ALIASES.may_load(deps.storage, &symbol)?;
+    if !matches!(&previous, Some(previous) if previous.denom == alias.denom) {
+        validate_no_route_timelock(
+            deps.as_ref(),
+            "denom aliases can not be given another denom while the route timelock is configured",
+        )?;
+    }
+
+    if symbol.is_empty() || alias.denom.is_empty() {
+        return Err(ContractError::InvalidDenomAlias {
+            reason: "symbol and denom must not be empty".to_string(),
+        });
+    }
+    if alias.exponent > MAX_DENOM_EXPONENT {
+        return Err(ContractError::InvalidDenomAlias {
+            reason: format!(
+                "exponent {} is larger than {MAX_DENOM_EXPONENT}",
+                alias.exponent
+            ),
+        });
+    }
+    // a symbol that is a denom itself would make that denom unreachable.
+    if DENOM_SYMBOLS.has(deps.storage, &symbol) || is_used_denom(deps.storage, &symbol)? {
+        return Err(ContractError::InvalidDenomAlias {
+            reason: format!("symbol {symbol} is a denom"),
+        });
+    }
+    if DENOM_ALIASES.has(deps.storage, &alias.denom) {
+        return Err(ContractError::InvalidDenomAlias {
+            reason: format!("denom {} is the symbol of another alias", alias.denom),
+        });
+    }
+    if let Some(other) = DENOM_SYMBOLS.may_load(deps.storage, &alias.denom)? {
+        if other != symbol {
+            return Err(ContractError::InvalidDenomAlias {
+                reason: format!("denom {} already has the symbol {other}", alias.denom),
+            });
+        }
+    }
+
+    if let Some(previous) = previous {
+        DENOM_SYMBOLS.remove(deps.storage, &previous.denom);
+    }
+    DENOM_ALIASES.save(deps.storage, &symbol, &alias)?;
+    DENOM_SYMBOLS.save(deps.storage, &alias.denom, &symbol)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_denom_alias")
+        .add_attribute("symbol", symbol)
+        .add_attribute("denom", alias.denom))
+}
+
+// remove_denom_alias removes the alias of the symbol. Only contract owner may execute this message.
+// Returns response with attributes on success.
+// Errors if:
+// - executed by anyone other than the owner
+// - the symbol has no alias
+pub fn remove_denom_alias(
+    deps: DepsMut,
+    info: MessageInfo,
+    symbol: String,
+) -> Result<Response, ContractError> {
+    validate_is_contract_owner(deps.as_ref(), info.sender)?;
+
+    let alias = DENOM_ALIASES.load(deps.storage, &symbol)?;
+    DENOM_ALIASES.remove(deps.storage, &symbol);
+    DENOM_SYMBOLS.remove(deps.storage, &alias.denom);
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_denom_alias")
+        .add_attribute("symbol", symbol))
+}
+
+// update_config updates the contract settings. Only contract owner may execute this message.
+// Settings that are not specified are left unchanged.
+// Returns response with attributes on success.
+// Errors if:
+// - executed by anyone other than the owner
+// - the route timelock is lowered, which would let routes be changed sooner than announced
+pub fn update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    update: ConfigUpdate,
+) -> Result<Response, ContractError> {
+    validate_is_contract_owner(deps.as_ref(), info.sender)?;
+
+    let mut config = CONFIG.load(deps.storage)?;
+    let route_timelock_seconds = config.route_timelock_seconds;
+    update.apply(&mut config);
+    if config.route_timelock_seconds < route_timelock_seconds {
+        return Err(ContractError::RouteTimelocked {
+            reason: "route timelock can not be lowered".to_string(),
+        });
+    }
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_config")
+        .add_attribute(
+            "swap_history_retention",
+            config.swap_history_retention.to_string(),
+        )
+        .add_attribute(
+            "router_twap_max_window",
+            config.router_twap_max_window.to_string(),
+        )
+        .add_attribute(
+            "max_spot_twap_deviation_percentage",
+            config.max_spot_twap_deviation_percentage.to_string(),
+        )
+        .add_attribute("max_route_hops", config.max_route_hops.to_string())
+        .add_attribute("min_pool_liquidity", config.min_pool_liquidity)
+        .add_attribute(
+            "route_timelock_seconds",
+            config.route_timelock_seconds.to_string(),
+        )
+        .add_attribute(
+            "denom_allowlist_enabled",
+            config.denom_allowlist_enabled.to_string(),
+        ))
+}
+
+// set_pair_config sets the pricing settings and swap limits for swaps
+// from input_denom to output_denom.
+// Denoms may be given as the symbols of denom aliases.
+// Only contract owner may execute this message.
+// Returns response with attributes on success.
+// Errors if:
+// - executed by anyone other than the owner
+// - the route timelock is configured and the change does not only tighten the pair config
+// - the oracle contract address is invalid
+// - the short TWAP window is empty or not shorter than the long TWAP window
+pub fn set_pair_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    input_denom: String,
+    output_denom: String,
+    pair_config: PairConfig,
+) -> Result<Response, ContractError> {
+    validate_is_contract_owner(deps.as_ref(), info.sender)?;
+
+    validate_pair_config(deps.as_ref(), &pair_config)?;
+
+    let input_denom = resolve_denom(deps.storage, &input_denom)?;
+    let output_denom = resolve_denom(deps.storage, &output_denom)?;
+    let current = PAIR_CONFIGS
+        .may_load(deps.storage, (&input_denom, &output_denom))?
+        .unwrap_or_default();
+    if !pair_config_only_restricts(&current, &pair_config) {
+        validate_no_route_timelock(
+            deps.as_ref(),
+            "pair configs can only be tightened while the route timelock is configured",
+        )?;
+    }
+    PAIR_CONFIGS.save(deps.storage, (&input_denom, &output_denom), &pair_config)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_pair_config")
+        .add_attribute("input_denom", input_denom)
+        .add_attribute("output_denom", output_denom))
 }
@@ -1,14 +1,33 @@
+use std::any::type_name;
 use std::ops::{Div, Mul};
+use std::str::FromStr;
 
-use cosmwasm_std::{Addr, Coin, Decimal, Deps, Timestamp, Uint128};
+use cosmwasm_std::{
+    Addr, BlockInfo, Coin, Decimal, Deps, DepsMut, Empty, Order, QuerierWrapper, StdError,
+    StdResult, Storage, SubMsg, Timestamp, Uint128, Uint256,
+};
+use cw_storage_plus::Bound;
 use osmosis_std::shim::Timestamp as OsmosisTimestamp;
-use osmosis_std::types::osmosis::gamm::v1beta1::{
-    MsgSwapExactAmountIn, QueryTotalPoolLiquidityRequest, SwapAmountInRoute,
+use osmosis_std::types::osmosis::epochs::v1beta1::EpochsQuerier;
+use osmosis_std::types::osmosis::poolmanager::v1beta1::{
+    MsgSwapExactAmountIn, PoolRequest, SpotPriceRequest, SwapAmountInRoute,
+    TotalPoolLiquidityRequest,
 };
 use osmosis_std::types::osmosis::twap::v1beta1::TwapQuerier;
 
 use crate::{
-    state::{OWNER, ROUTING_TABLE},
+    contract::{
+        RATE_LIMIT_BUCKETS, RATE_LIMIT_PRUNE_LIMIT, SWAP_HISTORY_PRUNE_LIMIT, SWAP_REPLY_ID,
+    },
+    msg::{OraclePriceResponse, OracleQueryMsg, RouteEntry, SwapType},
+    state::{
+        swap_history, PairConfig, PairStats, PoolType, PriceCheckpoint, PriceSource, RateLimit,
+        RateLimitWindow, RouteChange, RouteHop, RouteSegment, StepWiseState, SwapMsgReplyState,
+        SwapRecord, ALLOWED_DENOMS, BLOCKED_DENOMS, CONFIG, DENOM_ALIASES, FALLBACK_ROUTES, OWNER,
+        PAIR_CONFIGS, PAIR_STATS, POOL_ROUTES, PRICE_CHECKPOINTS, RATE_LIMITS, RATE_LIMIT_VOLUMES,
+        ROUTE_COMPOSITIONS, ROUTE_DEPENDENTS, ROUTE_EXPIRATIONS, ROUTE_HISTORY, ROUTING_TABLE,
+        SUSPENDED_ROUTES, SWAP_HISTORY_NEXT_ID,
+    },
     ContractError,
 };
 
@@ -23,21 +42,161 @@ pub fn validate_is_contract_owner(deps: Deps, sender: Addr) -> Result<(), Contra
     }
 }
 
+// resolve_denom returns the denom the symbol is an alias of,
+// or the given denom itself if it is not a symbol.
+pub fn resolve_denom(storage: &dyn Storage, denom: &str) -> StdResult<String> {
+    Ok(DENOM_ALIASES
+        .may_load(storage, denom)?
+        .map_or_else(|| denom.to_string(), |alias| alias.denom))
+}
+
+// resolve_pool_route resolves the symbols in the output denoms of the hops.
+pub fn resolve_pool_route(
+    storage: &dyn Storage,
+    pool_route: Vec<SwapAmountInRoute>,
+) -> StdResult<Vec<SwapAmountInRoute>> {
+    pool_route
+        .into_iter()
+        .map(|hop| {
+            Ok(SwapAmountInRoute {
+                pool_id: hop.pool_id,
+                token_out_denom: resolve_denom(storage, &hop.token_out_denom)?,
+            })
+        })
+        .collect()
+}
+
+// is_used_denom returns whether denom is the input or output denom of a stored route,
+// fallback route or pair config, the output denom of any of their hops,
+// or is on the allowlist or blocklist or has a rate limit.
+pub fn is_used_denom(storage: &dyn Storage, denom: &str) -> StdResult<bool> {
+    if ALLOWED_DENOMS.has(storage, denom)
+        || BLOCKED_DENOMS.has(storage, denom)
+        || RATE_LIMITS.has(storage, denom)
+    {
+        return Ok(true);
+    }
+    let is_pair_denom = |(input_denom, output_denom): &(String, String)| {
+        input_denom == denom || output_denom == denom
+    };
+    for item in ROUTING_TABLE.range(storage, None, None, Order::Ascending) {
+        let (pair, route) = item?;
+        if is_pair_denom(&pair) || route.iter().any(|hop| hop.token_out_denom == denom) {
+            return Ok(true);
+        }
+    }
+    for item in FALLBACK_ROUTES.range(storage, None, None, Order::Ascending) {
+        let (pair, routes) = item?;
+        if is_pair_denom(&pair)
+            || routes
+                .iter()
+                .flatten()
+                .any(|hop| hop.token_out_denom == denom)
+        {
+            return Ok(true);
+        }
+    }
+    for item in PAIR_CONFIGS.keys(storage, None, None, Order::Ascending) {
+        if is_pair_denom(&item?) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+// resolve_route_segments resolves the symbols in the denoms of the segments.
+pub fn resolve_route_segments(
+    storage: &dyn Storage,
+    segments: Vec<RouteSegment>,
+) -> StdResult<Vec<RouteSegment>> {
+    segments
+        .into_iter()
+        .map(|segment| match segment {
+            RouteSegment::Hop(hop) => Ok(RouteSegment::Hop(SwapAmountInRoute {
+                pool_id: hop.pool_id,
+                token_out_denom: resolve_denom(storage, &hop.token_out_denom)?,
+            })),
+            RouteSegment::Route {
+                input_denom,
+                output_denom,
+            } => Ok(RouteSegment::Route {
+                input_denom: resolve_denom(storage, &input_denom)?,
+                output_denom: resolve_denom(storage, &output_denom)?,
+            }),
+        })
+        .collect()
+}
+
+// validate_route_denoms validates if the router may swap every denom along the route.
+// Returns success if it may, error otherwise.
+// Errors if:
+// - any of the denoms is blocklisted
+// - the allowlist is enabled and any of the denoms is not allowlisted
+pub fn validate_route_denoms(
+    storage: &dyn Storage,
+    input_denom: &str,
+    output_denom: &str,
+    pool_route: &[SwapAmountInRoute],
+) -> Result<(), ContractError> {
+    let config = CONFIG.load(storage)?;
+    let denoms = [input_denom, output_denom]
+        .into_iter()
+        .chain(pool_route.iter().map(|hop| hop.token_out_denom.as_str()));
+    for denom in denoms {
+        if BLOCKED_DENOMS.has(storage, denom)
+            || (config.denom_allowlist_enabled && !ALLOWED_DENOMS.has(storage, denom))
+        {
+            return Err(ContractError::DenomNotAllowed {
+                denom: denom.to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
 // validate_pool_route validates if the pool route is valid.
-// Returns success if it is, error otherwise.
+// Returns the route along with the type of every pool if it is, error otherwise.
+// Errors if:
+// - the route has more hops than the configured maximum
+// - a pool does not hold the denom swapped in or out
+// - a pool holds less than the configured minimum liquidity of either denom
+// - the route visits a denom or uses a pool more than once
+// - the last denom of the route is not output_denom
 pub fn validate_pool_route(
     deps: Deps,
     input_denom: String,
     output_denom: String,
     pool_route: Vec<SwapAmountInRoute>,
-) -> Result<(), ContractError> {
+) -> Result<Vec<RouteHop>, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.max_route_hops > 0 && pool_route.len() as u64 > config.max_route_hops {
+        return Err(ContractError::InvalidPoolRoute {
+            reason: format!(
+                "route has {} hops, more than the maximum of {}",
+                pool_route.len(),
+                config.max_route_hops
+            ),
+        });
+    }
+    validate_route_denoms(deps.storage, &input_denom, &output_denom, &pool_route)?;
+
+    let mut visited_denoms = vec![input_denom.clone()];
     let mut current_denom_in = input_denom;
+    let mut route: Vec<RouteHop> = Vec::with_capacity(pool_route.len());
 
     // Iterate over each route
-    for route_part in &pool_route {
+    for route_part in pool_route {
+        if route.iter().any(|hop| hop.pool_id == route_part.pool_id) {
+            return Err(ContractError::InvalidPoolRoute {
+                reason: format!("pool id {} is used more than once", route_part.pool_id),
+            });
+        }
+
+        let pool_type = query_pool_type(deps, route_part.pool_id)?;
+
         // Query liqudity of the pool id specified by the route
-        // from the Osmosis chain.
-        let liquidity = QueryTotalPoolLiquidityRequest {
+        // from the poolmanager module, which covers every pool type.
+        let liquidity = TotalPoolLiquidityRequest {
             pool_id: route_part.pool_id,
         }
         .query(&deps.querier)?
@@ -45,32 +204,52 @@ pub fn validate_pool_route(
 
         // If the current denom to swap in does not match any of the denoms
         // in the pool, return error.
-        if !liquidity.iter().any(|coin| coin.denom == current_denom_in) {
-            return Result::Err(ContractError::InvalidPoolRoute {
-                reason: format!(
-                    "denom {} is not in pool id {}",
-                    current_denom_in, route_part.pool_id
-                ),
-            });
+        for denom in [&current_denom_in, &route_part.token_out_denom] {
+            let amount = match liquidity.iter().find(|coin| &coin.denom == denom) {
+                Some(coin) => Uint128::from_str(&coin.amount)?,
+                None => {
+                    return Err(ContractError::InvalidPoolRoute {
+                        reason: format!("denom {} is not in pool id {}", denom, route_part.pool_id),
+                    })
+                }
+            };
+
+            // Pools too shallow to swap through are rejected.
+            if amount.is_zero() {
+                return Err(ContractError::InvalidPoolRoute {
+                    reason: format!("pool id {} has no {} liquidity", route_part.pool_id, denom),
+                });
+            }
+            if amount < config.min_pool_liquidity {
+                return Err(ContractError::InvalidPoolRoute {
+                    reason: format!(
+                        "pool id {} holds {}{}, less than the minimum liquidity of {}",
+                        route_part.pool_id, amount, denom, config.min_pool_liquidity
+                    ),
+                });
+            }
         }
 
-        // If the denom to swap out does not match any of the denoms in the pool,
-        // return error.
-        if !liquidity
-            .iter()
-            .any(|coin| coin.denom == route_part.token_out_denom)
-        {
-            return Result::Err(ContractError::InvalidPoolRoute {
+        // Routes must not cycle back to a denom they already visited.
+        if visited_denoms.contains(&route_part.token_out_denom) {
+            return Err(ContractError::InvalidPoolRoute {
                 reason: format!(
-                    "denom {} is not in pool id {}",
-                    current_denom_in, route_part.pool_id
+                    "denom {} is visited more than once at pool id {}",
+                    route_part.token_out_denom, route_part.pool_id
                 ),
             });
         }
+        visited_denoms.push(route_part.token_out_denom.clone());
 
         // The denom to swap in for the next route is the denom
         // out for the current route.
         current_denom_in = route_part.token_out_denom.clone();
+
+        route.push(RouteHop {
+            pool_id: route_part.pool_id,
+            pool_type,
+            token_out_denom: route_part.token_out_denom,
+        });
     }
 
     // Make sure the final route output asset is the same as the expected output_denom
@@ -80,9 +259,763 @@ pub fn validate_pool_route(
         });
     }
 
+    Ok(route)
+}
+
+// reverse_route derives the route back to input_denom through the pools of
+// pool_route in reverse order, swapping out the intermediate denoms on the way back.
+//
+// Example:
+// input: OSMO
+// ROUTE = [ { pool_id: 1, token_out_denom: ATOM }, { pool_id: 2, token_out_denom: STAKE } ]
+// REVERSE = [ { pool_id: 2, token_out_denom: ATOM }, { pool_id: 1, token_out_denom: OSMO } ]
+pub fn reverse_route(
+    input_denom: &str,
+    pool_route: &[SwapAmountInRoute],
+) -> Vec<SwapAmountInRoute> {
+    let mut reverse = Vec::with_capacity(pool_route.len());
+    let mut denom_in = input_denom;
+
+    for hop in pool_route {
+        // swapping back through this pool yields the denom swapped in on the way there.
+        reverse.push(SwapAmountInRoute {
+            pool_id: hop.pool_id,
+            token_out_denom: denom_in.to_string(),
+        });
+        denom_in = &hop.token_out_denom;
+    }
+
+    reverse.reverse();
+    reverse
+}
+
+// validate_and_save_routes validates every route and saves them
+// only if all of them are valid. Denoms may be given as the symbols of denom aliases.
+// Returns the number of saved routes.
+// Errors if:
+// - any of the routes is an invalid pool route or expires before now
+// - other routes not replaced along with them are composed of the saved routes
+pub fn validate_and_save_routes(
+    mut deps: DepsMut,
+    routes: Vec<RouteEntry>,
+    changed_by: &Addr,
+    block: &BlockInfo,
+) -> Result<usize, ContractError> {
+    let routes = routes
+        .into_iter()
+        .map(|entry| {
+            validate_route_expiration(entry.expires_at, block.time)?;
+            let input_denom = resolve_denom(deps.storage, &entry.input_denom)?;
+            let output_denom = resolve_denom(deps.storage, &entry.output_denom)?;
+            let pool_route = resolve_pool_route(deps.storage, entry.pool_route)?;
+            let pool_route = validate_pool_route(
+                deps.as_ref(),
+                input_denom.clone(),
+                output_denom.clone(),
+                pool_route,
+            )?;
+            Ok((input_denom, output_denom, pool_route, entry.expires_at))
+        })
+        .collect::<Result<Vec<_>, ContractError>>()?;
+
+    let mut pairs = vec![];
+    for (input_denom, output_denom, pool_route, expires_at) in routes {
+        save_route(
+            deps.storage,
+            &input_denom,
+            &output_denom,
+            pool_route,
+            expires_at,
+            changed_by,
+            block,
+        )?;
+        pairs.push((input_denom, output_denom));
+    }
+
+    // composed routes saved in the same batch are no longer linked to their references.
+    for (input_denom, output_denom) in &pairs {
+        update_dependent_routes(
+            deps.branch(),
+            input_denom,
+            output_denom,
+            false,
+            changed_by,
+            block,
+        )?;
+    }
+
+    Ok(pairs.len())
+}
+
+// validate_route_expiration validates if the route expiration is in the future.
+// Returns success if it is or there is no expiration, error otherwise.
+pub fn validate_route_expiration(
+    expires_at: Option<Timestamp>,
+    now: Timestamp,
+) -> Result<(), ContractError> {
+    match expires_at {
+        Some(expires_at) if expires_at <= now => Err(ContractError::InvalidPoolRoute {
+            reason: format!("route expires at {}, before now", expires_at.seconds()),
+        }),
+        _ => Ok(()),
+    }
+}
+
+// validate_slippage_percentage validates if a slippage percentage requested for a swap
+// is at most 100. Returns success if it is, error otherwise.
+pub fn validate_slippage_percentage(percentage: Decimal) -> Result<(), ContractError> {
+    if percentage > Decimal::percent(10_000) {
+        return Err(ContractError::InvalidSlippagePercentage { percentage });
+    }
     Ok(())
 }
 
+// validate_pair_config validates the pricing settings and swap limits of a pair.
+// Returns success if they are valid, error otherwise.
+// Errors if:
+// - the oracle contract address is invalid
+// - the short TWAP window is empty or not shorter than the long TWAP window
+// - the maximum slippage percentage is more than 100
+// - the maximum input liquidity percentage is not between 0 and 100
+pub fn validate_pair_config(deps: Deps, pair_config: &PairConfig) -> Result<(), ContractError> {
+    if let PriceSource::Oracle { contract_address } = &pair_config.price_source {
+        deps.api.addr_validate(contract_address.as_str())?;
+    }
+
+    if let Some(windows) = &pair_config.twap_windows {
+        if windows.short_window_seconds == 0
+            || windows.short_window_seconds >= windows.long_window_seconds
+        {
+            return Err(ContractError::InvalidPairConfig {
+                reason: "short twap window must be positive and shorter than long twap window"
+                    .to_string(),
+            });
+        }
+    }
+
+    let hundred_percent = Decimal::percent(10_000);
+    if let Some(max_slippage_percentage) = pair_config.max_slippage_percentage {
+        if max_slippage_percentage > hundred_percent {
+            return Err(ContractError::InvalidPairConfig {
+                reason: "max slippage percentage must not be more than 100".to_string(),
+            });
+        }
+    }
+    if let Some(max_percentage) = pair_config.max_input_liquidity_percentage {
+        if max_percentage.is_zero() || max_percentage > hundred_percent {
+            return Err(ContractError::InvalidPairConfig {
+                reason: "max input liquidity percentage must be positive and not more than 100"
+                    .to_string(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+// pair_config_only_restricts returns whether changing the pair config from current to new
+// only tightens the swap limits or moves the pricing off an oracle onto the TWAP module,
+// so that the change may apply without waiting for the route timelock.
+pub fn pair_config_only_restricts(current: &PairConfig, new: &PairConfig) -> bool {
+    // a missing maximum does not limit anything.
+    let at_most = |new: Option<Decimal>, current: Option<Decimal>| match (new, current) {
+        (_, None) => true,
+        (Some(new), Some(current)) => new <= current,
+        (None, Some(_)) => false,
+    };
+    let price_source = new.price_source == current.price_source
+        || !matches!(new.price_source, PriceSource::Oracle { .. });
+    let twap_windows = match (&new.twap_windows, &current.twap_windows) {
+        (Some(new), Some(current)) => {
+            new.short_window_seconds == current.short_window_seconds
+                && new.long_window_seconds == current.long_window_seconds
+                && new.max_divergence_percentage <= current.max_divergence_percentage
+        }
+        (new, current) => new == current,
+    };
+
+    price_source
+        && twap_windows
+        && at_most(new.max_slippage_percentage, current.max_slippage_percentage)
+        && new.min_input_amount.unwrap_or_default() >= current.min_input_amount.unwrap_or_default()
+        && at_most(
+            new.max_input_liquidity_percentage,
+            current.max_input_liquidity_percentage,
+        )
+}
+
+// validate_min_output_floor validates the explicit minimum output of a swap of input_coin
+// against the maximum slippage percentage of the pair, priced the same way as swaps
+// with max slippage percentage, so that a low minimum output can not bypass the cap.
+// Returns success if the pair has no maximum or the minimum output is not below it,
+// error otherwise.
+// Errors if:
+// - the minimum output is less than the output at the maximum slippage of the pair
+// - the swap could not be priced
+pub fn validate_min_output_floor(
+    deps: Deps,
+    input_coin: &Coin,
+    min_output: &Coin,
+    now: Timestamp,
+) -> Result<(), ContractError> {
+    let max_percentage = match PAIR_CONFIGS
+        .may_load(deps.storage, (&input_coin.denom, &min_output.denom))?
+        .and_then(|pair_config| pair_config.max_slippage_percentage)
+    {
+        Some(max_percentage) => max_percentage,
+        None => return Ok(()),
+    };
+
+    let floor = calculate_min_output_from_twap(
+        deps,
+        input_coin.clone(),
+        min_output.denom.clone(),
+        now,
+        max_percentage,
+    )?;
+    if min_output.amount < floor.amount {
+        return Err(ContractError::PairLimitExceeded {
+            reason: format!(
+                "minimum output of {} is less than the {} allowed by the maximum slippage of {}%",
+                min_output, floor, max_percentage
+            ),
+        });
+    }
+
+    Ok(())
+}
+
+// validate_pair_limits validates a swap of input_coin to output_denom through the route
+// against the limits in the pair config.
+// Returns success if the swap is within the limits, error otherwise.
+// Errors if:
+// - the requested max slippage percentage is more than the pair allows
+// - the input amount is less than the pair minimum
+// - the input amount is more than the pair allows of the first pool liquidity
+pub fn validate_pair_limits(
+    deps: Deps,
+    input_coin: &Coin,
+    output_denom: &str,
+    swap_type: &SwapType,
+    pool_route: &[SwapAmountInRoute],
+) -> Result<(), ContractError> {
+    let pair_config = PAIR_CONFIGS
+        .may_load(deps.storage, (&input_coin.denom, output_denom))?
+        .unwrap_or_default();
+
+    if let (SwapType::MaxSlippagePercentage(percentage), Some(max_percentage)) =
+        (swap_type, pair_config.max_slippage_percentage)
+    {
+        if *percentage > max_percentage {
+            return Err(ContractError::PairLimitExceeded {
+                reason: format!(
+                    "slippage of {}% is more than the maximum of {}%",
+                    percentage, max_percentage
+                ),
+            });
+        }
+    }
+
+    if let Some(min_input_amount) = pair_config.min_input_amount {
+        if input_coin.amount < min_input_amount {
+            return Err(ContractError::PairLimitExceeded {
+                reason: format!(
+                    "input of {} is less than the minimum of {}{}",
+                    input_coin, min_input_amount, input_coin.denom
+                ),
+            });
+        }
+    }
+
+    if let (Some(max_percentage), Some(first_hop)) = (
+        pair_config.max_input_liquidity_percentage,
+        pool_route.first(),
+    ) {
+        let liquidity = TotalPoolLiquidityRequest {
+            pool_id: first_hop.pool_id,
+        }
+        .query(&deps.querier)?
+        .liquidity
+        .into_iter()
+        .find(|coin| coin.denom == input_coin.denom)
+        .map(|coin| Uint128::from_str(&coin.amount))
+        .transpose()?
+        .unwrap_or_default();
+
+        if liquidity.is_zero()
+            || Decimal::from_ratio(input_coin.amount, liquidity)
+                > max_percentage * Decimal::percent(1)
+        {
+            return Err(ContractError::PairLimitExceeded {
+                reason: format!(
+                    "input of {} is more than {}% of the {} liquidity of pool id {}",
+                    input_coin, max_percentage, input_coin.denom, first_hop.pool_id
+                ),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+// validate_rate_limit validates the swap volume limit of a denom.
+// Returns success if it is valid, error otherwise.
+// Errors if:
+// - the rolling window is empty
+// - the epoch identifier is unknown to the epochs module
+pub fn validate_rate_limit(deps: Deps, rate_limit: &RateLimit) -> Result<(), ContractError> {
+    match &rate_limit.window {
+        RateLimitWindow::Seconds(0) => Err(ContractError::InvalidRateLimit {
+            reason: "rate limit window must be positive".to_string(),
+        }),
+        RateLimitWindow::Seconds(_) => Ok(()),
+        RateLimitWindow::Epoch { identifier } => {
+            EpochsQuerier::new(&deps.querier).current_epoch(identifier.clone())?;
+            Ok(())
+        }
+    }
+}
+
+// save_route saves the route from input_denom to output_denom to the routing table,
+// keeps the pool index in sync with it and records the change in the route history.
+// The route is treated as missing after expires_at, if given, and a suspension of
+// the replaced route is lifted.
+// Returns the replaced route, empty if there was none.
+pub fn save_route(
+    storage: &mut dyn Storage,
+    input_denom: &str,
+    output_denom: &str,
+    route: Vec<RouteHop>,
+    expires_at: Option<Timestamp>,
+    changed_by: &Addr,
+    block: &BlockInfo,
+) -> StdResult<Vec<RouteHop>> {
+    unlink_route_composition(storage, input_denom, output_denom)?;
+    let previous_route = unindex_route(storage, input_denom, output_denom)?.unwrap_or_default();
+    for hop in &route {
+        POOL_ROUTES.save(storage, (hop.pool_id, input_denom, output_denom), &Empty {})?;
+    }
+    ROUTING_TABLE.save(storage, (input_denom, output_denom), &route)?;
+    match expires_at {
+        Some(expires_at) => {
+            ROUTE_EXPIRATIONS.save(storage, (input_denom, output_denom), &expires_at)?
+        }
+        None => ROUTE_EXPIRATIONS.remove(storage, (input_denom, output_denom)),
+    }
+    SUSPENDED_ROUTES.remove(storage, (input_denom, output_denom));
+
+    record_route_change(
+        storage,
+        input_denom,
+        output_denom,
+        RouteChange {
+            changed_by: changed_by.clone(),
+            block_height: block.height,
+            time: block.time,
+            previous_route: previous_route.clone(),
+            new_route: route,
+        },
+    )?;
+
+    Ok(previous_route)
+}
+
+// remove_route removes the route from input_denom to output_denom from the routing table
+// along with its pool index entries and records the change in the route history.
+// The routes composed of it can not be expanded anymore and are removed along with it.
+// Returns the removed composed routes. Does nothing if there is no such route.
+pub fn remove_route(
+    storage: &mut dyn Storage,
+    input_denom: &str,
+    output_denom: &str,
+    changed_by: &Addr,
+    block: &BlockInfo,
+) -> StdResult<Vec<(String, String)>> {
+    unlink_route_composition(storage, input_denom, output_denom)?;
+    let previous_route = match unindex_route(storage, input_denom, output_denom)? {
+        Some(previous_route) => previous_route,
+        None => return Ok(vec![]),
+    };
+    ROUTING_TABLE.remove(storage, (input_denom, output_denom));
+    ROUTE_EXPIRATIONS.remove(storage, (input_denom, output_denom));
+    SUSPENDED_ROUTES.remove(storage, (input_denom, output_denom));
+
+    record_route_change(
+        storage,
+        input_denom,
+        output_denom,
+        RouteChange {
+            changed_by: changed_by.clone(),
+            block_height: block.height,
+            time: block.time,
+            previous_route,
+            new_route: vec![],
+        },
+    )?;
+
+    let mut removed = vec![];
+    for (dependent_input, dependent_output) in ROUTE_DEPENDENTS
+        .may_load(storage, (input_denom, output_denom))?
+        .unwrap_or_default()
+    {
+        // a route composed of several removed routes is removed only once.
+        if !ROUTING_TABLE.has(storage, (&dependent_input, &dependent_output)) {
+            continue;
+        }
+        let dependents = remove_route(
+            storage,
+            &dependent_input,
+            &dependent_output,
+            changed_by,
+            block,
+        )?;
+        removed.push((dependent_input, dependent_output));
+        removed.extend(dependents);
+    }
+    ROUTE_DEPENDENTS.remove(storage, (input_denom, output_denom));
+
+    Ok(removed)
+}
+
+// expand_route_segments expands the segments of a route into a flat route,
+// replacing every referenced pair by its stored route.
+// Errors if:
+// - a referenced pair has no route, or its route has expired or is suspended
+pub fn expand_route_segments(
+    storage: &dyn Storage,
+    segments: &[RouteSegment],
+    now: Timestamp,
+) -> Result<Vec<SwapAmountInRoute>, ContractError> {
+    let mut route = vec![];
+    for segment in segments {
+        match segment {
+            RouteSegment::Hop(hop) => route.push(hop.clone()),
+            RouteSegment::Route {
+                input_denom,
+                output_denom,
+            } => {
+                let stored = if is_route_expired(storage, input_denom, output_denom, now)?
+                    || SUSPENDED_ROUTES.has(storage, (input_denom, output_denom))
+                {
+                    None
+                } else {
+                    ROUTING_TABLE.may_load(storage, (input_denom, output_denom))?
+                };
+                let stored = stored.ok_or_else(|| ContractError::InvalidPoolRoute {
+                    reason: format!("no route from {input_denom} to {output_denom} is stored"),
+                })?;
+                route.extend(to_swap_route(stored));
+            }
+        }
+    }
+    Ok(route)
+}
+
+// validate_route_composition validates that the segments do not reference the route from
+// input_denom to output_denom, directly or through the routes composed of it.
+// Returns success if they do not, error otherwise.
+pub fn validate_route_composition(
+    storage: &dyn Storage,
+    input_denom: &str,
+    output_denom: &str,
+    segments: &[RouteSegment],
+) -> Result<(), ContractError> {
+    let mut dependents = vec![(input_denom.to_string(), output_denom.to_string())];
+    let mut i = 0;
+    while i < dependents.len() {
+        let (dependent_input, dependent_output) = dependents[i].clone();
+        for dependent in ROUTE_DEPENDENTS
+            .may_load(storage, (&dependent_input, &dependent_output))?
+            .unwrap_or_default()
+        {
+            if !dependents.contains(&dependent) {
+                dependents.push(dependent);
+            }
+        }
+        i += 1;
+    }
+
+    for (reference_input, reference_output) in route_references(segments) {
+        if dependents.contains(&(reference_input.clone(), reference_output.clone())) {
+            return Err(ContractError::InvalidPoolRoute {
+                reason: format!(
+                    "route from {reference_input} to {reference_output} depends on the route from {input_denom} to {output_denom}"
+                ),
+            });
+        }
+    }
+    Ok(())
+}
+
+// link_route_composition records the segments of the route from input_denom to output_denom
+// so that it is updated along with the routes it references.
+// Does nothing if no segment references a stored route.
+pub fn link_route_composition(
+    storage: &mut dyn Storage,
+    input_denom: &str,
+    output_denom: &str,
+    segments: Vec<RouteSegment>,
+) -> StdResult<()> {
+    let references = route_references(&segments);
+    if references.is_empty() {
+        return Ok(());
+    }
+
+    let dependent = (input_denom.to_string(), output_denom.to_string());
+    for (reference_input, reference_output) in references {
+        ROUTE_DEPENDENTS.update(
+            storage,
+            (&reference_input, &reference_output),
+            |dependents| -> StdResult<_> {
+                let mut dependents = dependents.unwrap_or_default();
+                if !dependents.contains(&dependent) {
+                    dependents.push(dependent.clone());
+                }
+                Ok(dependents)
+            },
+        )?;
+    }
+    ROUTE_COMPOSITIONS.save(storage, (input_denom, output_denom), &segments)
+}
+
+// unlink_route_composition removes the segments of the route from input_denom to output_denom
+// along with its entries in the dependents of the routes it references.
+// Does nothing if the route is not composed of other routes.
+fn unlink_route_composition(
+    storage: &mut dyn Storage,
+    input_denom: &str,
+    output_denom: &str,
+) -> StdResult<()> {
+    let segments = match ROUTE_COMPOSITIONS.may_load(storage, (input_denom, output_denom))? {
+        Some(segments) => segments,
+        None => return Ok(()),
+    };
+
+    for (reference_input, reference_output) in route_references(&segments) {
+        let mut dependents = ROUTE_DEPENDENTS
+            .may_load(storage, (&reference_input, &reference_output))?
+            .unwrap_or_default();
+        dependents.retain(|(dependent_input, dependent_output)| {
+            dependent_input != input_denom || dependent_output != output_denom
+        });
+        if dependents.is_empty() {
+            ROUTE_DEPENDENTS.remove(storage, (&reference_input, &reference_output));
+        } else {
+            ROUTE_DEPENDENTS.save(storage, (&reference_input, &reference_output), &dependents)?;
+        }
+    }
+    ROUTE_COMPOSITIONS.remove(storage, (input_denom, output_denom));
+    Ok(())
+}
+
+// route_references returns the distinct pairs whose stored route the segments reference.
+fn route_references(segments: &[RouteSegment]) -> Vec<(String, String)> {
+    let mut references: Vec<(String, String)> = vec![];
+    for segment in segments {
+        if let RouteSegment::Route {
+            input_denom,
+            output_denom,
+        } = segment
+        {
+            let reference = (input_denom.clone(), output_denom.clone());
+            if !references.contains(&reference) {
+                references.push(reference);
+            }
+        }
+    }
+    references
+}
+
+// update_dependent_routes re-expands the routes composed of the route from input_denom
+// to output_denom, and the routes composed of those in turn, keeping their expiration.
+// Returns the updated pairs.
+// Errors if:
+// - there are such routes and propagate is false
+// - any of the re-expanded routes is an invalid pool route
+pub fn update_dependent_routes(
+    deps: DepsMut,
+    input_denom: &str,
+    output_denom: &str,
+    propagate: bool,
+    changed_by: &Addr,
+    block: &BlockInfo,
+) -> Result<Vec<(String, String)>, ContractError> {
+    let mut dependents = ROUTE_DEPENDENTS
+        .may_load(deps.storage, (input_denom, output_denom))?
+        .unwrap_or_default();
+    if !dependents.is_empty() && !propagate {
+        let pairs: Vec<String> = dependents
+            .iter()
+            .map(|(dependent_input, dependent_output)| {
+                format!("{dependent_input} -> {dependent_output}")
+            })
+            .collect();
+        return Err(ContractError::InvalidPoolRoute {
+            reason: format!(
+                "route from {input_denom} to {output_denom} is used by the routes {}, set propagate to update them",
+                pairs.join(", ")
+            ),
+        });
+    }
+
+    // a route is re-expanded again if a route it references is updated after it.
+    let mut updated = vec![];
+    let mut i = 0;
+    while i < dependents.len() {
+        let (dependent_input, dependent_output) = dependents[i].clone();
+        let segments =
+            ROUTE_COMPOSITIONS.load(deps.storage, (&dependent_input, &dependent_output))?;
+        let expires_at =
+            ROUTE_EXPIRATIONS.may_load(deps.storage, (&dependent_input, &dependent_output))?;
+
+        let route = expand_route_segments(deps.storage, &segments, block.time)?;
+        let route = validate_pool_route(
+            deps.as_ref(),
+            dependent_input.clone(),
+            dependent_output.clone(),
+            route,
+        )?;
+        save_route(
+            deps.storage,
+            &dependent_input,
+            &dependent_output,
+            route,
+            expires_at,
+            changed_by,
+            block,
+        )?;
+        link_route_composition(deps.storage, &dependent_input, &dependent_output, segments)?;
+
+        for dependent in ROUTE_DEPENDENTS
+            .may_load(deps.storage, (&dependent_input, &dependent_output))?
+            .unwrap_or_default()
+        {
+            if !dependents[i + 1..].contains(&dependent) {
+                dependents.push(dependent);
+            }
+        }
+        let dependent = (dependent_input, dependent_output);
+        if !updated.contains(&dependent) {
+            updated.push(dependent);
+        }
+        i += 1;
+    }
+
+    Ok(updated)
+}
+
+// load_route loads the route from input_denom to output_denom.
+// Returns the same not found error as a missing route if the route has expired
+// or is suspended.
+pub fn load_route(
+    storage: &dyn Storage,
+    input_denom: &str,
+    output_denom: &str,
+    now: Timestamp,
+) -> StdResult<Vec<RouteHop>> {
+    if is_route_expired(storage, input_denom, output_denom, now)?
+        || SUSPENDED_ROUTES.has(storage, (input_denom, output_denom))
+    {
+        return Err(StdError::not_found(type_name::<Vec<RouteHop>>()));
+    }
+    ROUTING_TABLE.load(storage, (input_denom, output_denom))
+}
+
+// is_route_expired returns whether the route from input_denom to output_denom
+// has an expiration that is not after now.
+pub fn is_route_expired(
+    storage: &dyn Storage,
+    input_denom: &str,
+    output_denom: &str,
+    now: Timestamp,
+) -> StdResult<bool> {
+    let expires_at = ROUTE_EXPIRATIONS.may_load(storage, (input_denom, output_denom))?;
+    Ok(matches!(expires_at, Some(expires_at) if expires_at <= now))
+}
+
+// unindex_route removes the pool index entries of the route from input_denom to output_denom.
+// Returns the route, None if there is no such route.
+fn unindex_route(
+    storage: &mut dyn Storage,
+    input_denom: &str,
+    output_denom: &str,
+) -> StdResult<Option<Vec<RouteHop>>> {
+    let route = ROUTING_TABLE.may_load(storage, (input_denom, output_denom))?;
+    for hop in route.iter().flatten() {
+        POOL_ROUTES.remove(storage, (hop.pool_id, input_denom, output_denom));
+    }
+    Ok(route)
+}
+
+// record_route_change records a change of the route from input_denom to output_denom
+// under the next version of the pair, starting at 1.
+fn record_route_change(
+    storage: &mut dyn Storage,
+    input_denom: &str,
+    output_denom: &str,
+    change: RouteChange,
+) -> StdResult<()> {
+    let last_version = ROUTE_HISTORY
+        .prefix((input_denom, output_denom))
+        .keys(storage, None, None, Order::Descending)
+        .next()
+        .transpose()?;
+    let version = last_version.unwrap_or_default() + 1;
+
+    ROUTE_HISTORY.save(storage, (input_denom, output_denom, version), &change)
+}
+
+// pool_ids formats the pool ids of the route as a comma separated list.
+pub fn pool_ids(route: &[RouteHop]) -> String {
+    route
+        .iter()
+        .map(|hop| hop.pool_id.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// route_rejection_reason validates the route from input_denom to output_denom
+// the same way as validate_pool_route.
+// Returns the reason the route is rejected, None if it is valid.
+// Returns error if the route could not be validated, such as on a failed pool query.
+pub fn route_rejection_reason(
+    deps: Deps,
+    input_denom: String,
+    output_denom: String,
+    pool_route: Vec<SwapAmountInRoute>,
+) -> Result<Option<String>, ContractError> {
+    match validate_pool_route(deps, input_denom, output_denom, pool_route) {
+        Ok(_) => Ok(None),
+        Err(ContractError::InvalidPoolRoute { reason }) => Ok(Some(reason)),
+        Err(err @ ContractError::DenomNotAllowed { .. }) => Ok(Some(err.to_string())),
+        Err(err) => Err(err),
+    }
+}
+
+// query_pool_type returns the type of the pool with the given id from the poolmanager module.
+// Returns error if the pool does not exist or is of an unsupported type.
+pub fn query_pool_type(deps: Deps, pool_id: u64) -> Result<PoolType, ContractError> {
+    let pool = PoolRequest { pool_id }
+        .query(&deps.querier)?
+        .pool
+        .unwrap_or_default();
+
+    match pool.type_url.as_str() {
+        "/osmosis.gamm.v1beta1.Pool" => Ok(PoolType::Balancer),
+        "/osmosis.gamm.poolmodels.stableswap.v1beta1.Pool" => Ok(PoolType::Stableswap),
+        "/osmosis.concentratedliquidity.v1beta1.Pool" => Ok(PoolType::Concentrated),
+        "/osmosis.cosmwasmpool.v1beta1.CosmWasmPool" => Ok(PoolType::CosmWasm),
+        type_url => Err(ContractError::InvalidPoolRoute {
+            reason: format!("pool id {pool_id} has unsupported type {type_url:?}"),
+        }),
+    }
+}
+
+// to_swap_route converts a stored route into the route of an Osmosis swap message.
+pub fn to_swap_route(route: Vec<RouteHop>) -> Vec<SwapAmountInRoute> {
+    route.into_iter().map(SwapAmountInRoute::from).collect()
+}
+
 // generate_swap_msg generates and returns an Osmosis
 // MsgSwapExactAmountIn with sender, input token and min_output_token.
 // Returns error if there is no supported route
@@ -92,18 +1025,110 @@ pub fn generate_swap_msg(
     sender: Addr,
     input_token: Coin,
     min_output_token: Coin,
+    now: Timestamp,
 ) -> Result<MsgSwapExactAmountIn, ContractError> {
     // get trade route
-    let route = ROUTING_TABLE.load(deps.storage, (&input_token.denom, &min_output_token.denom))?;
+    let route = load_route(
+        deps.storage,
+        &input_token.denom,
+        &min_output_token.denom,
+        now,
+    )?;
 
     Ok(MsgSwapExactAmountIn {
         sender: sender.into_string(),
-        routes: route,
+        routes: to_swap_route(route),
         token_in: Some(input_token.into()),
         token_out_min_amount: min_output_token.amount.to_string(),
     })
 }
 
+// validate_fallback_route validates a fallback route of a swap of input_coin to output_denom
+// with the checks the primary route gets when the swap is created.
+// Returns success if the swap may fall back to the route, error otherwise.
+// Errors if:
+// - the route of the pair has expired
+// - any denom along the route is blocklisted, or not allowlisted if the allowlist is enabled
+// - the swap exceeds the limits of the pair on the route
+pub fn validate_fallback_route(
+    deps: Deps,
+    input_coin: &Coin,
+    output_denom: &str,
+    swap_type: &SwapType,
+    route: &[SwapAmountInRoute],
+    now: Timestamp,
+) -> Result<(), ContractError> {
+    if is_route_expired(deps.storage, &input_coin.denom, output_denom, now)? {
+        return Err(ContractError::InvalidPoolRoute {
+            reason: format!(
+                "route from {} to {} has expired",
+                input_coin.denom, output_denom
+            ),
+        });
+    }
+    validate_route_denoms(deps.storage, &input_coin.denom, output_denom, route)?;
+    validate_pair_limits(deps, input_coin, output_denom, swap_type, route)
+}
+
+// generate_swap_submsg wraps the swap message of the reply state into a sub message.
+// The reply is also requested on failure if the swap can fall back to another route,
+// is itself a fallback whose failure is reported along with the previous ones,
+// or refunds the input on failure.
+pub fn generate_swap_submsg(swap_msg_reply_state: &SwapMsgReplyState) -> SubMsg {
+    let swap_msg = swap_msg_reply_state.swap_msg.clone();
+    if swap_msg_reply_state.best_effort
+        || !swap_msg_reply_state.fallback_routes.is_empty()
+        || !swap_msg_reply_state.failures.is_empty()
+    {
+        SubMsg::reply_always(swap_msg, SWAP_REPLY_ID)
+    } else {
+        SubMsg::reply_on_success(swap_msg, SWAP_REPLY_ID)
+    }
+}
+
+// generate_hop_swap_msg generates an Osmosis MsgSwapExactAmountIn for the
+// current hop of a step-wise swap with a minimum output derived from the price
+// of that hop. The last hop is also bound by the minimum output of the whole swap.
+pub fn generate_hop_swap_msg(
+    deps: Deps,
+    sender: Addr,
+    step_wise: &StepWiseState,
+    hop_input: Coin,
+    now: Timestamp,
+) -> Result<MsgSwapExactAmountIn, ContractError> {
+    let hop_route = step_wise.route[step_wise.hop].clone();
+
+    // price the hop with the price source of the whole pair.
+    let price_source = PAIR_CONFIGS
+        .may_load(
+            deps.storage,
+            (&step_wise.input_coin.denom, &step_wise.min_output.denom),
+        )?
+        .unwrap_or_default()
+        .price_source;
+    let hop_price = price_source.route_price(
+        deps,
+        std::slice::from_ref(&hop_route),
+        &hop_input.denom,
+        now.minus_seconds(1),
+    )?;
+
+    let percentage = step_wise.hop_max_slippage_percentage.div(Uint128::new(100));
+    let mut min_out: Uint128 = hop_input.amount.mul(hop_price - hop_price.mul(percentage));
+    if step_wise.hop + 1 == step_wise.route.len() {
+        min_out = min_out.max(step_wise.min_output.amount);
+    }
+    deps.api
+        .debug(&format!("hop {} min: {min_out}", step_wise.hop));
+
+    Ok(MsgSwapExactAmountIn {
+        sender: sender.into_string(),
+        routes: vec![hop_route],
+        token_in: Some(hop_input.into()),
+        token_out_min_amount: min_out.to_string(),
+    })
+}
+
 pub fn calculate_min_output_from_twap(
     deps: Deps,
     input_token: Coin,
@@ -112,7 +1137,12 @@ pub fn calculate_min_output_from_twap(
     percentage_impact: Decimal,
 ) -> Result<Coin, ContractError> {
     // get trade route
-    let route = ROUTING_TABLE.load(deps.storage, (&input_token.denom, &output_denom))?;
+    let route = to_swap_route(load_route(
+        deps.storage,
+        &input_token.denom,
+        &output_denom,
+        now,
+    )?);
     if route.is_empty() {
         return Err(ContractError::InvalidPoolRoute {
             reason: format!("No route foung for {} -> {output_denom}", input_token.denom),
@@ -120,46 +1150,57 @@ pub fn calculate_min_output_from_twap(
     }
 
     let percentage = percentage_impact.div(Uint128::new(100));
+    let max_deviation = CONFIG
+        .load(deps.storage)?
+        .max_spot_twap_deviation_percentage;
+    let pair_config = PAIR_CONFIGS
+        .may_load(deps.storage, (&input_token.denom, &output_denom))?
+        .unwrap_or_default();
 
-    let mut twap_price: Decimal = Decimal::one();
+    let mut twap_price = match pair_config.twap_windows {
+        Some(windows) => {
+            let short_twap = pair_config.price_source.route_price(
+                deps,
+                &route,
+                &input_token.denom,
+                now.minus_seconds(windows.short_window_seconds),
+            )?;
+            let long_twap = pair_config.price_source.route_price(
+                deps,
+                &route,
+                &input_token.denom,
+                now.minus_seconds(windows.long_window_seconds),
+            )?;
 
-    // When swapping from input to output, we need to quote the price in the input token
-    // For example when selling uosmo to buy uion:
-    // price of <out> is X<in> (i.e.: price of uion is X uosmo)
-    let mut quote_denom = input_token.denom;
+            if exceeds_deviation(short_twap, long_twap, windows.max_divergence_percentage) {
+                return Err(ContractError::TwapWindowDivergence {
+                    short_twap,
+                    long_twap,
+                    max_divergence_percentage: windows.max_divergence_percentage,
+                });
+            }
 
-    let start_time = now.minus_seconds(1);
-    let start_time = OsmosisTimestamp {
-        seconds: start_time.seconds() as i64,
-        nanos: 0_i32,
+            // use the price giving the higher minimum output, so that a window
+            // skewed in the attacker's favor cannot loosen the slippage bound.
+            short_twap.max(long_twap)
+        }
+        None => pair_config.price_source.route_price(
+            deps,
+            &route,
+            &input_token.denom,
+            now.minus_seconds(1),
+        )?,
     };
 
-    for route_part in route {
-        deps.api.debug(&format!("route part: {route_part:?}"));
-
-        let twap = TwapQuerier::new(&deps.querier)
-            .arithmetic_twap_to_now(
-                route_part.pool_id,
-                route_part.token_out_denom.clone(), // base_asset
-                quote_denom.clone(),                // quote_asset
-                Some(start_time.clone()),
-            )?
-            .arithmetic_twap;
-
-        deps.api.debug(&format!("twap = {twap}"));
-
-        let current_twap: Decimal = twap.parse().map_err(|_e| ContractError::CustomError {
-            val: "Invalid twap value received from the chain".to_string(),
-        })?;
-
-        twap_price = twap_price.checked_mul(current_twap.into()).map_err(|_e| {
-            ContractError::CustomError {
-                val: format!("Invalid value for twap price: {twap_price} * {twap}"),
-            }
-        })?;
-
-        // the current output is the input for the next route_part
-        quote_denom = route_part.token_out_denom;
+    // only query the spot price when the deviation guard is enabled.
+    if !max_deviation.is_zero() {
+        let spot_price = calculate_route_spot_price(deps, &route, &input_token.denom)?;
+        validate_spot_price_deviation(
+            spot_price,
+            twap_price,
+            &pair_config.price_source,
+            max_deviation,
+        )?;
     }
 
     twap_price = twap_price - twap_price.mul(percentage);
@@ -172,3 +1213,700 @@ pub fn calculate_min_output_from_twap(
 
     Ok(Coin::new(min_out.into(), output_denom))
 }
+
+impl PriceSource {
+    // name returns the kind of the price source, as used in error messages.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PriceSource::ArithmeticTwap => "arithmetic TWAP",
+            PriceSource::GeometricTwap => "geometric TWAP",
+            PriceSource::Oracle { .. } => "oracle",
+        }
+    }
+
+    // route_price returns the price of one unit of input_denom in the output
+    // denom of the route, i.e. the expected amount out per amount in.
+    // TWAP sources multiply the twap of every pool along the route from start_time
+    // until now. Oracle sources ignore start_time and query the oracle for the pair.
+    pub fn route_price(
+        &self,
+        deps: Deps,
+        route: &[SwapAmountInRoute],
+        input_denom: &str,
+        start_time: Timestamp,
+    ) -> Result<Decimal, ContractError> {
+        if let PriceSource::Oracle { contract_address } = self {
+            let output_denom = route
+                .last()
+                .map(|route_part| route_part.token_out_denom.clone())
+                .unwrap_or_default();
+            let res: OraclePriceResponse = deps.querier.query_wasm_smart(
+                contract_address,
+                &OracleQueryMsg::Price {
+                    base_denom: input_denom.to_string(),
+                    quote_denom: output_denom,
+                },
+            )?;
+
+            deps.api.debug(&format!("oracle price = {}", res.price));
+
+            return Ok(res.price);
+        }
+
+        let mut twap_price: Decimal = Decimal::one();
+
+        // When swapping from input to output, we need to quote the price in the input token
+        // For example when selling uosmo to buy uion:
+        // price of <out> is X<in> (i.e.: price of uion is X uosmo)
+        let mut quote_denom = input_denom.to_string();
+
+        let start_time = OsmosisTimestamp {
+            seconds: start_time.seconds() as i64,
+            nanos: 0_i32,
+        };
+
+        for route_part in route {
+            deps.api.debug(&format!("route part: {route_part:?}"));
+
+            let twap_querier = TwapQuerier::new(&deps.querier);
+            let twap = match self {
+                PriceSource::GeometricTwap => {
+                    twap_querier
+                        .geometric_twap_to_now(
+                            route_part.pool_id,
+                            route_part.token_out_denom.clone(), // base_asset
+                            quote_denom.clone(),                // quote_asset
+                            Some(start_time.clone()),
+                        )?
+                        .geometric_twap
+                }
+                _ => {
+                    twap_querier
+                        .arithmetic_twap_to_now(
+                            route_part.pool_id,
+                            route_part.token_out_denom.clone(), // base_asset
+                            quote_denom.clone(),                // quote_asset
+                            Some(start_time.clone()),
+                        )?
+                        .arithmetic_twap
+                }
+            };
+
+            deps.api.debug(&format!("twap = {twap}"));
+
+            let current_twap: Decimal = twap.parse().map_err(|_e| ContractError::CustomError {
+                val: "Invalid twap value received from the chain".to_string(),
+            })?;
+
+            twap_price =
+                twap_price
+                    .checked_mul(current_twap)
+                    .map_err(|_e| ContractError::CustomError {
+                        val: format!("Invalid value for twap price: {twap_price} * {twap}"),
+                    })?;
+
+            // the current output is the input for the next route_part
+            quote_denom = route_part.token_out_denom.clone();
+        }
+
+        Ok(twap_price)
+    }
+}
+
+// calculate_route_spot_price returns the product of the current spot price
+// of every pool along the route, quoted the same way as the TWAP price sources.
+pub fn calculate_route_spot_price(
+    deps: Deps,
+    route: &[SwapAmountInRoute],
+    input_denom: &str,
+) -> Result<Decimal, ContractError> {
+    let mut spot_price: Decimal = Decimal::one();
+    let mut quote_denom = input_denom.to_string();
+
+    for route_part in route {
+        let spot = SpotPriceRequest {
+            pool_id: route_part.pool_id,
+            base_asset_denom: route_part.token_out_denom.clone(),
+            quote_asset_denom: quote_denom.clone(),
+        }
+        .query(&deps.querier)?
+        .spot_price;
+
+        deps.api.debug(&format!("spot = {spot}"));
+
+        let current_spot: Decimal = spot.parse().map_err(|_e| ContractError::CustomError {
+            val: "Invalid spot price value received from the chain".to_string(),
+        })?;
+
+        spot_price =
+            spot_price
+                .checked_mul(current_spot)
+                .map_err(|_e| ContractError::CustomError {
+                    val: format!("Invalid value for spot price: {spot_price} * {spot}"),
+                })?;
+
+        // the current output is the input for the next route_part
+        quote_denom = route_part.token_out_denom.clone();
+    }
+
+    Ok(spot_price)
+}
+
+// validate_spot_price_deviation validates that the spot price deviates from the
+// reference price given by price_source by at most max_deviation_percentage percent
+// of the reference price. Returns success if it does, error otherwise.
+pub fn validate_spot_price_deviation(
+    spot_price: Decimal,
+    reference_price: Decimal,
+    price_source: &PriceSource,
+    max_deviation_percentage: Decimal,
+) -> Result<(), ContractError> {
+    if exceeds_deviation(spot_price, reference_price, max_deviation_percentage) {
+        return Err(ContractError::SpotPriceDeviation {
+            spot_price,
+            reference_price,
+            price_source: price_source.name().to_string(),
+            max_deviation_percentage,
+        });
+    }
+
+    Ok(())
+}
+
+// exceeds_deviation returns whether price deviates from reference_price
+// by more than max_deviation_percentage percent of reference_price.
+pub fn exceeds_deviation(
+    price: Decimal,
+    reference_price: Decimal,
+    max_deviation_percentage: Decimal,
+) -> bool {
+    let deviation = if price > reference_price {
+        price - reference_price
+    } else {
+        reference_price - price
+    };
+
+    deviation > reference_price.mul(max_deviation_percentage.div(Uint128::new(100)))
+}
+
+// record_swap_history appends a completed swap to the swap history and
+// prunes up to SWAP_HISTORY_PRUNE_LIMIT of the oldest records beyond the configured retention cap.
+pub fn record_swap_history(storage: &mut dyn Storage, record: SwapRecord) -> StdResult<()> {
+    let retention = CONFIG.load(storage)?.swap_history_retention;
+    let id = SWAP_HISTORY_NEXT_ID.may_load(storage)?.unwrap_or_default();
+
+    if retention > 0 {
+        swap_history().save(storage, id, &record)?;
+    }
+    SWAP_HISTORY_NEXT_ID.save(storage, &(id + 1))?;
+
+    // ids are sequential, so every record older than the last `retention`
+    // ids is expired. Iteration stops at the first record that is kept.
+    let expired: Vec<u64> = swap_history()
+        .keys(storage, None, None, Order::Ascending)
+        .take_while(|key| matches!(key, Ok(key) if key.saturating_add(retention) <= id))
+        .take(SWAP_HISTORY_PRUNE_LIMIT)
+        .collect::<StdResult<_>>()?;
+    for key in expired {
+        swap_history().remove(storage, key)?;
+    }
+
+    Ok(())
+}
+
+// update_pair_stats adds a completed swap to the cumulative statistics
+// of its (input_denom, output_denom) pair.
+// The last execution price is kept if the price of the swap does not fit a `Decimal`.
+pub fn update_pair_stats(
+    storage: &mut dyn Storage,
+    input_coin: &Coin,
+    output_coin: &Coin,
+    now: Timestamp,
+) -> StdResult<()> {
+    let key = (input_coin.denom.as_str(), output_coin.denom.as_str());
+    let stats = PAIR_STATS.may_load(storage, key)?;
+
+    let stats = PairStats {
+        total_input: stats
+            .as_ref()
+            .map_or(Uint128::zero(), |stats| stats.total_input)
+            .checked_add(input_coin.amount)?,
+        total_output: stats
+            .as_ref()
+            .map_or(Uint128::zero(), |stats| stats.total_output)
+            .checked_add(output_coin.amount)?,
+        swap_count: stats.as_ref().map_or(0, |stats| stats.swap_count) + 1,
+        last_execution_price: Decimal::checked_from_ratio(output_coin.amount, input_coin.amount)
+            .unwrap_or_else(|_| stats.map_or(Decimal::zero(), |stats| stats.last_execution_price)),
+        last_timestamp: now,
+    };
+
+    PAIR_STATS.save(storage, key, &stats)
+}
+
+// update_price_accumulator adds a checkpoint with the execution price of a
+// completed swap to the price accumulator of its (input_denom, output_denom) pair.
+// Checkpoints no longer needed for the longest router TWAP window are pruned.
+// Does nothing if the execution price does not fit a `Decimal`, the previous price stays in effect.
+pub fn update_price_accumulator(
+    storage: &mut dyn Storage,
+    input_coin: &Coin,
+    output_coin: &Coin,
+    now: Timestamp,
+) -> StdResult<()> {
+    let price = match Decimal::checked_from_ratio(output_coin.amount, input_coin.amount) {
+        Ok(price) => price,
+        Err(_) => return Ok(()),
+    };
+    let pair = (input_coin.denom.as_str(), output_coin.denom.as_str());
+    let now = now.seconds();
+
+    let cumulative_price = match latest_price_checkpoint(storage, pair, now)? {
+        Some((time, checkpoint)) => cumulative_price_at(time, &checkpoint, now)?,
+        None => Uint256::zero(),
+    };
+    PRICE_CHECKPOINTS.save(
+        storage,
+        (pair.0, pair.1, now),
+        &PriceCheckpoint {
+            cumulative_price,
+            price,
+        },
+    )?;
+
+    // keep the newest checkpoint before the cutoff so that
+    // the longest window can still be interpolated.
+    let cutoff = now.saturating_sub(CONFIG.load(storage)?.router_twap_max_window);
+    let mut expired: Vec<u64> = PRICE_CHECKPOINTS
+        .prefix(pair)
+        .keys(
+            storage,
+            None,
+            Some(Bound::exclusive(cutoff)),
+            Order::Ascending,
+        )
+        .collect::<StdResult<_>>()?;
+    expired.pop();
+    for time in expired {
+        PRICE_CHECKPOINTS.remove(storage, (pair.0, pair.1, time));
+    }
+
+    Ok(())
+}
+
+// latest_price_checkpoint returns the newest price checkpoint of the pair
+// at or before the given time together with its time in seconds.
+pub fn latest_price_checkpoint(
+    storage: &dyn Storage,
+    pair: (&str, &str),
+    time: u64,
+) -> StdResult<Option<(u64, PriceCheckpoint)>> {
+    PRICE_CHECKPOINTS
+        .prefix(pair)
+        .range(
+            storage,
+            None,
+            Some(Bound::inclusive(time)),
+            Order::Descending,
+        )
+        .next()
+        .transpose()
+}
+
+// cumulative_price_at extends the cumulative price of a checkpoint taken at
+// checkpoint_time with the checkpoint price up to the given time.
+pub fn cumulative_price_at(
+    checkpoint_time: u64,
+    checkpoint: &PriceCheckpoint,
+    time: u64,
+) -> StdResult<Uint256> {
+    let elapsed = Uint256::from(time - checkpoint_time);
+    let accrued = Uint256::from(checkpoint.price.atomics()).checked_mul(elapsed)?;
+    Ok(checkpoint.cumulative_price.checked_add(accrued)?)
+}
+
+// rate_limit_window returns the first and the current window key of the rate limit window
+// at the given time: buckets of the time for rolling windows, counting the volumes
+// of up to RATE_LIMIT_BUCKETS buckets covering the window, and epoch numbers for epoch windows.
+fn rate_limit_window(
+    querier: &QuerierWrapper,
+    window: &RateLimitWindow,
+    now: Timestamp,
+) -> StdResult<(u64, u64)> {
+    match window {
+        RateLimitWindow::Seconds(seconds) => {
+            let bucket_seconds = seconds.div_ceil(RATE_LIMIT_BUCKETS);
+            let buckets = seconds.div_ceil(bucket_seconds);
+            let current = now.seconds() / bucket_seconds;
+            Ok(((current + 1).saturating_sub(buckets), current))
+        }
+        RateLimitWindow::Epoch { identifier } => {
+            let epoch = EpochsQuerier::new(querier)
+                .current_epoch(identifier.clone())?
+                .current_epoch as u64;
+            Ok((epoch, epoch))
+        }
+    }
+}
+
+// window_volume returns the input amount of denom swapped by sender,
+// or by all senders for the empty sender, within the window keys [first, current].
+fn window_volume(
+    storage: &dyn Storage,
+    denom: &str,
+    sender: &str,
+    (first, current): (u64, u64),
+) -> StdResult<Uint128> {
+    (first..=current).try_fold(Uint128::zero(), |total, key| {
+        let volume = RATE_LIMIT_VOLUMES
+            .may_load(storage, (denom, key, sender))?
+            .unwrap_or_default();
+        Ok(total.checked_add(volume)?)
+    })
+}
+
+// prune_rate_limit_volumes removes up to RATE_LIMIT_PRUNE_LIMIT volumes of denom,
+// of any sender, recorded outside of the window keys [first, current].
+fn prune_rate_limit_volumes(
+    storage: &mut dyn Storage,
+    denom: &str,
+    (first, current): (u64, u64),
+) -> StdResult<()> {
+    let volumes = RATE_LIMIT_VOLUMES.sub_prefix(denom);
+    let stale: Vec<(u64, String)> = volumes
+        .keys(
+            storage,
+            None,
+            Some(Bound::exclusive((first, ""))),
+            Order::Ascending,
+        )
+        .chain(volumes.keys(
+            storage,
+            Some(Bound::inclusive((current + 1, ""))),
+            None,
+            Order::Ascending,
+        ))
+        .take(RATE_LIMIT_PRUNE_LIMIT)
+        .collect::<StdResult<_>>()?;
+    for (key, sender) in stale {
+        RATE_LIMIT_VOLUMES.remove(storage, (denom, key, &sender));
+    }
+    Ok(())
+}
+
+// remaining_rate_limit returns the input amount of denom that can still be swapped
+// in the current window of its rate limit, in total and by the sender if given.
+// Returns None for the amounts that are not limited.
+pub fn remaining_rate_limit(
+    deps: Deps,
+    denom: &str,
+    sender: Option<&Addr>,
+    now: Timestamp,
+) -> StdResult<(Option<Uint128>, Option<Uint128>)> {
+    let rate_limit = match RATE_LIMITS.may_load(deps.storage, denom)? {
+        Some(rate_limit) => rate_limit,
+        None => return Ok((None, None)),
+    };
+    let window = rate_limit_window(&deps.querier, &rate_limit.window, now)?;
+
+    let remaining =
+        rate_limit
+            .max_volume
+            .saturating_sub(window_volume(deps.storage, denom, "", window)?);
+    let sender_remaining = match (sender, rate_limit.max_volume_per_sender) {
+        (Some(sender), Some(max_volume)) => Some(max_volume.saturating_sub(window_volume(
+            deps.storage,
+            denom,
+            sender.as_str(),
+            window,
+        )?)),
+        _ => None,
+    };
+
+    Ok((Some(remaining), sender_remaining))
+}
+
+// consume_rate_limit records the input coin swapped by sender against the rate limit
+// of its denom and prunes the volumes recorded outside of the current window.
+// Returns success if the denom is not rate limited or the coin fits the limit, error otherwise.
+// Errors if:
+// - the amount exceeds the remaining capacity of the denom
+// - the amount exceeds the remaining capacity of the sender
+pub fn consume_rate_limit(
+    deps: DepsMut,
+    sender: &Addr,
+    input_coin: &Coin,
+    now: Timestamp,
+) -> Result<(), ContractError> {
+    let rate_limit = match RATE_LIMITS.may_load(deps.storage, &input_coin.denom)? {
+        Some(rate_limit) => rate_limit,
+        None => return Ok(()),
+    };
+    let (first, current) = rate_limit_window(&deps.querier, &rate_limit.window, now)?;
+    prune_rate_limit_volumes(deps.storage, &input_coin.denom, (first, current))?;
+
+    let mut limits = vec![("", rate_limit.max_volume)];
+    if let Some(max_volume) = rate_limit.max_volume_per_sender {
+        limits.push((sender.as_str(), max_volume));
+    }
+    for (account, max_volume) in limits {
+        let remaining = max_volume.saturating_sub(window_volume(
+            deps.storage,
+            &input_coin.denom,
+            account,
+            (first, current),
+        )?);
+        if input_coin.amount > remaining {
+            let scope = if account.is_empty() {
+                ""
+            } else {
+                " of the sender"
+            };
+            return Err(ContractError::RateLimitExceeded {
+                reason: format!(
+                    "swapping {} exceeds the remaining capacity{} of {}{}",
+                    input_coin, scope, remaining, input_coin.denom
+                ),
+            });
+        }
+
+        RATE_LIMIT_VOLUMES.update(
+            deps.storage,
+            (&input_coin.denom, current, account),
+            |volume| -> StdResult<_> {
+                Ok(volume.unwrap_or_default().checked_add(input_coin.amount)?)
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+// release_rate_limit gives the input coin of a swap refunded in the same block back to the
+// capacity of its denom and of the sender, undoing consume_rate_limit.
+// Does nothing if the denom is not rate limited.
+pub fn release_rate_limit(
+    deps: DepsMut,
+    sender: &Addr,
+    input_coin: &Coin,
+    now: Timestamp,
+) -> Result<(), ContractError> {
+    let rate_limit = match RATE_LIMITS.may_load(deps.storage, &input_coin.denom)? {
+        Some(rate_limit) => rate_limit,
+        None => return Ok(()),
+    };
+    let (_, current) = rate_limit_window(&deps.querier, &rate_limit.window, now)?;
+
+    for account in ["", sender.as_str()] {
+        let key = (input_coin.denom.as_str(), current, account);
+        if let Some(volume) = RATE_LIMIT_VOLUMES.may_load(deps.storage, key)? {
+            let volume = volume.saturating_sub(input_coin.amount);
+            if volume.is_zero() {
+                RATE_LIMIT_VOLUMES.remove(deps.storage, key);
+            } else {
+                RATE_LIMIT_VOLUMES.save(deps.storage, key, &volume)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use cosmwasm_std::testing::{mock_dependencies, MockStorage};
+
+    use super::*;
+    use crate::state::Config;
+
+    #[test]
+    fn reverse_route_walks_pools_backwards() {
+        let hop = |pool_id: u64, token_out_denom: &str| SwapAmountInRoute {
+            pool_id,
+            token_out_denom: token_out_denom.to_string(),
+        };
+
+        assert_eq!(
+            reverse_route("uosmo", &[hop(2, "stake"), hop(3, "uion")]),
+            vec![hop(3, "stake"), hop(2, "uosmo")]
+        );
+        assert_eq!(
+            reverse_route("uosmo", &[hop(1, "uion")]),
+            vec![hop(1, "uosmo")]
+        );
+        assert_eq!(reverse_route("uosmo", &[]), vec![]);
+    }
+
+    #[test]
+    fn spot_twap_deviation_within_bounds() {
+        let twap = Decimal::from_str("2").unwrap();
+        let max_deviation = Decimal::from_str("5").unwrap();
+
+        let source = PriceSource::ArithmeticTwap;
+
+        validate_spot_price_deviation(twap, twap, &source, max_deviation).unwrap();
+        validate_spot_price_deviation(
+            Decimal::from_str("2.1").unwrap(),
+            twap,
+            &source,
+            max_deviation,
+        )
+        .unwrap();
+        validate_spot_price_deviation(
+            Decimal::from_str("1.9").unwrap(),
+            twap,
+            &source,
+            max_deviation,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn spot_twap_deviation_out_of_bounds() {
+        let twap = Decimal::from_str("2").unwrap();
+        let max_deviation = Decimal::from_str("5").unwrap();
+
+        for spot in ["2.11", "1.89"] {
+            let err = validate_spot_price_deviation(
+                Decimal::from_str(spot).unwrap(),
+                twap,
+                &PriceSource::ArithmeticTwap,
+                max_deviation,
+            )
+            .unwrap_err();
+            assert!(matches!(err, ContractError::SpotPriceDeviation { .. }));
+        }
+    }
+
+    #[test]
+    fn rate_limit_window_spans_fixed_buckets() {
+        let deps = mock_dependencies();
+        let querier = QuerierWrapper::new(&deps.querier);
+        let now = Timestamp::from_seconds(600);
+
+        // buckets of 6 seconds.
+        let window = RateLimitWindow::Seconds(60);
+        assert_eq!(
+            rate_limit_window(&querier, &window, now).unwrap(),
+            (91, 100)
+        );
+        // windows shorter than RATE_LIMIT_BUCKETS seconds use buckets of a second.
+        let window = RateLimitWindow::Seconds(5);
+        assert_eq!(
+            rate_limit_window(&querier, &window, now).unwrap(),
+            (596, 600)
+        );
+    }
+
+    #[test]
+    fn stale_rate_limit_volumes_are_pruned_over_several_swaps() {
+        let mut storage = MockStorage::new();
+        let volume = Uint128::new(1);
+        for key in 0..15 {
+            RATE_LIMIT_VOLUMES
+                .save(&mut storage, ("uosmo", key, "idle_sender"), &volume)
+                .unwrap();
+        }
+        RATE_LIMIT_VOLUMES
+            .save(&mut storage, ("uosmo", 20, ""), &volume)
+            .unwrap();
+        let count = |storage: &MockStorage| {
+            RATE_LIMIT_VOLUMES
+                .sub_prefix("uosmo")
+                .keys(storage, None, None, Order::Ascending)
+                .count()
+        };
+
+        prune_rate_limit_volumes(&mut storage, "uosmo", (20, 29)).unwrap();
+        assert_eq!(count(&storage), 16 - RATE_LIMIT_PRUNE_LIMIT);
+        prune_rate_limit_volumes(&mut storage, "uosmo", (20, 29)).unwrap();
+        assert_eq!(count(&storage), 1);
+        assert_eq!(
+            window_volume(&storage, "uosmo", "", (20, 29)).unwrap(),
+            volume
+        );
+    }
+
+    #[test]
+    fn swap_history_with_maximum_retention_is_kept() {
+        let mut storage = MockStorage::new();
+        CONFIG
+            .save(
+                &mut storage,
+                &Config {
+                    swap_history_retention: u64::MAX,
+                    router_twap_max_window: 0,
+                    max_spot_twap_deviation_percentage: Decimal::zero(),
+                    max_route_hops: 0,
+                    min_pool_liquidity: Uint128::zero(),
+                    route_timelock_seconds: 0,
+                    denom_allowlist_enabled: false,
+                },
+            )
+            .unwrap();
+        let record = SwapRecord {
+            sender: Addr::unchecked("sender"),
+            input_coin: Coin::new(1000, "uosmo"),
+            output_coin: Coin::new(900, "uion"),
+            pool_ids: vec![1],
+            block_height: 1,
+            time: Timestamp::from_seconds(1),
+            swap_type: SwapType::MinOutputAmount(Uint128::new(1)),
+        };
+
+        record_swap_history(&mut storage, record.clone()).unwrap();
+        record_swap_history(&mut storage, record).unwrap();
+        assert_eq!(
+            swap_history()
+                .keys(&storage, None, None, Order::Ascending)
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn pair_stats_keep_the_last_price_that_does_not_fit_a_decimal() {
+        let mut storage = MockStorage::new();
+        let now = Timestamp::from_seconds(1);
+
+        update_pair_stats(
+            &mut storage,
+            &Coin::new(1000, "uosmo"),
+            &Coin::new(2000, "uion"),
+            now,
+        )
+        .unwrap();
+        update_pair_stats(
+            &mut storage,
+            &Coin::new(1, "uosmo"),
+            &Coin::new(10u128.pow(21), "uion"),
+            now,
+        )
+        .unwrap();
+
+        let stats = PAIR_STATS.load(&storage, ("uosmo", "uion")).unwrap();
+        assert_eq!(stats.swap_count, 2);
+        assert_eq!(stats.total_output, Uint128::new(10u128.pow(21) + 2000));
+        assert_eq!(stats.last_execution_price, Decimal::percent(200));
+    }
+
+    #[test]
+    fn price_accumulator_skips_a_price_that_does_not_fit_a_decimal() {
+        let mut storage = MockStorage::new();
+
+        update_price_accumulator(
+            &mut storage,
+            &Coin::new(1, "uosmo"),
+            &Coin::new(10u128.pow(21), "uion"),
+            Timestamp::from_seconds(1),
+        )
+        .unwrap();
+
+        assert_eq!(
+            latest_price_checkpoint(&storage, ("uosmo", "uion"), 1).unwrap(),
+            None
+        );
+    }
+}
@@ -1,11 +1,25 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Coin, Decimal, Uint128};
-use osmosis_std::types::osmosis::gamm::v1beta1::SwapAmountInRoute;
+use cosmwasm_std::{BlockInfo, Coin, Decimal, Timestamp, Uint128};
+use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountInRoute;
+
+use crate::state::{
+    Config, DenomAlias, PairConfig, PairStats, ProposedRoute, RateLimit, RouteChange, RouteHop,
+    RouteSegment, SwapRecord,
+};
 
 /// Message type for `instantiate` entry_point
 #[cw_serde]
 pub struct InstantiateMsg {
     pub owner: String,
+    /// Settings applied over the defaults.
+    #[serde(default)]
+    pub config: ConfigUpdate,
+    /// Routes set as by `SetRoutes`.
+    #[serde(default)]
+    pub routes: Vec<RouteEntry>,
+    /// Pricing settings set as by `SetPairConfig`.
+    #[serde(default)]
+    pub pair_configs: Vec<PairConfigEntry>,
 }
 
 /// Message type for `execute` entry_point
@@ -14,12 +28,90 @@ pub enum ExecuteMsg {
     SetRoute {
         input_denom: String,
         output_denom: String,
-        pool_route: Vec<SwapAmountInRoute>,
+        /// Hops of the route, or pairs whose stored route is used in their place.
+        pool_route: Vec<RouteSegment>,
+        /// Also set the reverse route from output_denom to input_denom.
+        #[serde(default)]
+        bidirectional: bool,
+        /// Time after which the route is treated as missing.
+        #[serde(default)]
+        expires_at: Option<Timestamp>,
+        /// Re-expand the routes composed of this route instead of refusing the change.
+        #[serde(default)]
+        propagate: bool,
+    },
+    SetRoutes {
+        routes: Vec<RouteEntry>,
     },
     Swap {
         input_coin: Coin,
         output_denom: String,
         swap_type: SwapType,
+        deadline: Option<Deadline>,
+        execution_mode: Option<ExecutionMode>,
+    },
+    UpdateConfig(ConfigUpdate),
+    SetPairConfig {
+        input_denom: String,
+        output_denom: String,
+        pair_config: PairConfig,
+    },
+    DisablePool {
+        pool_id: u64,
+    },
+    PruneInvalidRoutes {
+        start_after: Option<(String, String)>,
+        limit: Option<u32>,
+    },
+    RestoreRoute {
+        input_denom: String,
+        output_denom: String,
+    },
+    RemoveExpiredRoutes {
+        start_after: Option<(String, String)>,
+        limit: Option<u32>,
+    },
+    SetFallbackRoutes {
+        input_denom: String,
+        output_denom: String,
+        fallback_routes: Vec<Vec<SwapAmountInRoute>>,
+    },
+    /// Sets the swap volume limit of an input denom, removes it if not given.
+    SetRateLimit {
+        denom: String,
+        rate_limit: Option<RateLimit>,
+    },
+    UpdateDenomList {
+        list: DenomList,
+        add: Vec<String>,
+        remove: Vec<String>,
+    },
+    SetDenomAlias {
+        symbol: String,
+        alias: DenomAlias,
+    },
+    RemoveDenomAlias {
+        symbol: String,
+    },
+    ProposeRoute {
+        input_denom: String,
+        output_denom: String,
+        pool_route: Vec<SwapAmountInRoute>,
+        #[serde(default)]
+        bidirectional: bool,
+        #[serde(default)]
+        expires_at: Option<Timestamp>,
+        /// Re-expand the routes composed of this route on execution instead of refusing it.
+        #[serde(default)]
+        propagate: bool,
+    },
+    ExecuteProposedRoute {
+        input_denom: String,
+        output_denom: String,
+    },
+    CancelProposedRoute {
+        input_denom: String,
+        output_denom: String,
     },
 }
 
@@ -34,6 +126,158 @@ pub enum QueryMsg {
         input_denom: String,
         output_denom: String,
     },
+    #[returns(ListRoutesResponse)]
+    ListRoutes {
+        start_after: Option<(String, String)>,
+        limit: Option<u32>,
+    },
+    #[returns(RouteHistoryResponse)]
+    RouteHistory {
+        input_denom: String,
+        output_denom: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    #[returns(RoutesByPoolResponse)]
+    RoutesByPool { pool_id: u64 },
+    #[returns(RouteDependentsResponse)]
+    RouteDependents {
+        input_denom: String,
+        output_denom: String,
+    },
+    #[returns(RouteHealthResponse)]
+    RouteHealth {
+        start_after: Option<(String, String)>,
+        limit: Option<u32>,
+    },
+    #[returns(GetFallbackRoutesResponse)]
+    GetFallbackRoutes {
+        input_denom: String,
+        output_denom: String,
+    },
+    #[returns(ProposedRoutesResponse)]
+    ProposedRoutes {
+        start_after: Option<(String, String)>,
+        limit: Option<u32>,
+    },
+    #[returns(ValidateRouteResponse)]
+    ValidateRoute {
+        input_denom: String,
+        output_denom: String,
+        pool_route: Vec<SwapAmountInRoute>,
+    },
+    #[returns(ExportRoutesResponse)]
+    ExportRoutes {},
+    /// Input amount of denom that can still be swapped in the current rate limit window.
+    #[returns(RemainingCapacityResponse)]
+    RemainingCapacity {
+        denom: String,
+        sender: Option<String>,
+    },
+    #[returns(DenomListResponse)]
+    DenomList {
+        list: DenomList,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(DenomAliasesResponse)]
+    DenomAliases {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Amount of coin, given by denom or symbol, in the display unit of its alias.
+    #[returns(DisplayAmountResponse)]
+    DisplayAmount { coin: Coin },
+    #[returns(GetConfigResponse)]
+    GetConfig {},
+    #[returns(GetPairConfigResponse)]
+    GetPairConfig {
+        input_denom: String,
+        output_denom: String,
+    },
+    #[returns(SwapHistoryResponse)]
+    SwapHistory {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    #[returns(SwapHistoryResponse)]
+    SwapHistoryBySender {
+        sender: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    #[returns(SwapHistoryResponse)]
+    SwapHistoryByPair {
+        input_denom: String,
+        output_denom: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    #[returns(PairStatsResponse)]
+    PairStats {
+        input_denom: String,
+        output_denom: String,
+    },
+    #[returns(AllPairStatsResponse)]
+    AllPairStats {
+        start_after: Option<(String, String)>,
+        limit: Option<u32>,
+    },
+    #[returns(RouterTwapResponse)]
+    RouterTwap {
+        input_denom: String,
+        output_denom: String,
+        window_seconds: u64,
+    },
+}
+
+/// Contract settings to update, settings that are not specified are left unchanged.
+#[cw_serde]
+#[derive(Default)]
+pub struct ConfigUpdate {
+    pub swap_history_retention: Option<u64>,
+    pub router_twap_max_window: Option<u64>,
+    pub max_spot_twap_deviation_percentage: Option<Decimal>,
+    pub max_route_hops: Option<u64>,
+    pub min_pool_liquidity: Option<Uint128>,
+    pub route_timelock_seconds: Option<u64>,
+    pub denom_allowlist_enabled: Option<bool>,
+}
+
+impl ConfigUpdate {
+    // apply overrides the settings of config that are specified.
+    pub fn apply(self, config: &mut Config) {
+        if let Some(swap_history_retention) = self.swap_history_retention {
+            config.swap_history_retention = swap_history_retention;
+        }
+        if let Some(router_twap_max_window) = self.router_twap_max_window {
+            config.router_twap_max_window = router_twap_max_window;
+        }
+        if let Some(max_deviation) = self.max_spot_twap_deviation_percentage {
+            config.max_spot_twap_deviation_percentage = max_deviation;
+        }
+        if let Some(max_route_hops) = self.max_route_hops {
+            config.max_route_hops = max_route_hops;
+        }
+        if let Some(min_pool_liquidity) = self.min_pool_liquidity {
+            config.min_pool_liquidity = min_pool_liquidity;
+        }
+        if let Some(route_timelock_seconds) = self.route_timelock_seconds {
+            config.route_timelock_seconds = route_timelock_seconds;
+        }
+        if let Some(denom_allowlist_enabled) = self.denom_allowlist_enabled {
+            config.denom_allowlist_enabled = denom_allowlist_enabled;
+        }
+    }
+}
+
+/// Pricing settings and swap limits for swaps from input_denom to output_denom,
+/// as set by `SetPairConfig`.
+#[cw_serde]
+pub struct PairConfigEntry {
+    pub input_denom: String,
+    pub output_denom: String,
+    pub pair_config: PairConfig,
 }
 
 #[cw_serde]
@@ -43,7 +287,208 @@ pub struct GetOwnerResponse {
 
 #[cw_serde]
 pub struct GetRouteResponse {
+    pub pool_route: Vec<RouteHop>,
+}
+
+/// Route from input_denom to output_denom, as set by `SetRoute`.
+#[cw_serde]
+pub struct RouteEntry {
+    pub input_denom: String,
+    pub output_denom: String,
     pub pool_route: Vec<SwapAmountInRoute>,
+    #[serde(default)]
+    pub expires_at: Option<Timestamp>,
+}
+
+#[cw_serde]
+pub struct ListRoutesEntry {
+    pub input_denom: String,
+    pub output_denom: String,
+    pub pool_route: Vec<RouteHop>,
+    pub expires_at: Option<Timestamp>,
+    // whether the route has expired and is treated as missing.
+    pub expired: bool,
+}
+
+#[cw_serde]
+pub struct ListRoutesResponse {
+    pub routes: Vec<ListRoutesEntry>,
+}
+
+#[cw_serde]
+pub struct ExportRoutesResponse {
+    pub routes: Vec<RouteEntry>,
+}
+
+#[cw_serde]
+pub struct ValidateRouteResponse {
+    pub valid: bool,
+    // reason the route is rejected, if it is.
+    pub reason: Option<String>,
+}
+
+#[cw_serde]
+pub struct RouteHistoryEntry {
+    pub version: u64,
+    pub change: RouteChange,
+}
+
+#[cw_serde]
+pub struct RouteHistoryResponse {
+    pub changes: Vec<RouteHistoryEntry>,
+}
+
+#[cw_serde]
+pub struct DenomPair {
+    pub input_denom: String,
+    pub output_denom: String,
+}
+
+#[cw_serde]
+pub struct RoutesByPoolResponse {
+    pub routes: Vec<DenomPair>,
+}
+
+#[cw_serde]
+pub struct RouteDependentsResponse {
+    pub routes: Vec<DenomPair>,
+}
+
+#[cw_serde]
+pub struct RouteHealthEntry {
+    pub input_denom: String,
+    pub output_denom: String,
+    pub healthy: bool,
+    // reason the route is rejected, if it is.
+    pub reason: Option<String>,
+    // whether the route is suspended by `PruneInvalidRoutes`.
+    pub suspended: bool,
+}
+
+#[cw_serde]
+pub struct RouteHealthResponse {
+    pub routes: Vec<RouteHealthEntry>,
+}
+
+#[cw_serde]
+pub struct GetFallbackRoutesResponse {
+    pub fallback_routes: Vec<Vec<RouteHop>>,
+}
+
+#[cw_serde]
+pub struct ProposedRouteEntry {
+    pub input_denom: String,
+    pub output_denom: String,
+    pub proposal: ProposedRoute,
+    // earliest time the route can be executed under the current route timelock.
+    pub executable_after: Timestamp,
+}
+
+#[cw_serde]
+pub struct ProposedRoutesResponse {
+    pub proposals: Vec<ProposedRouteEntry>,
+}
+
+#[cw_serde]
+pub struct RemainingCapacityResponse {
+    pub rate_limit: Option<RateLimit>,
+    /// Remaining capacity of all senders, not limited if not set.
+    pub remaining: Option<Uint128>,
+    /// Remaining capacity of the sender, not limited if not set.
+    pub sender_remaining: Option<Uint128>,
+}
+
+/// Owner-managed list of denoms checked along every route.
+#[cw_serde]
+pub enum DenomList {
+    /// Denoms the router may swap when the allowlist is enabled in the config.
+    Allowlist,
+    /// Denoms the router must never swap.
+    Blocklist,
+}
+
+#[cw_serde]
+pub struct DenomListResponse {
+    pub denoms: Vec<String>,
+}
+
+#[cw_serde]
+pub struct DenomAliasEntry {
+    pub symbol: String,
+    pub alias: DenomAlias,
+}
+
+#[cw_serde]
+pub struct DenomAliasesResponse {
+    pub aliases: Vec<DenomAliasEntry>,
+}
+
+#[cw_serde]
+pub struct DisplayAmountResponse {
+    pub amount: Decimal,
+    pub display: String,
+}
+
+#[cw_serde]
+pub struct GetConfigResponse {
+    pub config: Config,
+}
+
+#[cw_serde]
+pub struct GetPairConfigResponse {
+    pub pair_config: PairConfig,
+}
+
+#[cw_serde]
+pub struct SwapHistoryEntry {
+    pub id: u64,
+    pub record: SwapRecord,
+}
+
+#[cw_serde]
+pub struct SwapHistoryResponse {
+    pub records: Vec<SwapHistoryEntry>,
+}
+
+#[cw_serde]
+pub struct PairStatsResponse {
+    pub stats: PairStats,
+}
+
+#[cw_serde]
+pub struct PairStatsEntry {
+    pub input_denom: String,
+    pub output_denom: String,
+    pub stats: PairStats,
+}
+
+#[cw_serde]
+pub struct AllPairStatsResponse {
+    pub pairs: Vec<PairStatsEntry>,
+}
+
+#[cw_serde]
+pub struct RouterTwapResponse {
+    // time-weighted average amount of output received per unit of input.
+    pub twap: Decimal,
+}
+
+/// Query message an external oracle contract must accept
+/// to be used as a `PriceSource::Oracle`.
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum OracleQueryMsg {
+    /// Price of one unit of `base_denom` expressed in `quote_denom`.
+    #[returns(OraclePriceResponse)]
+    Price {
+        base_denom: String,
+        quote_denom: String,
+    },
+}
+
+#[cw_serde]
+pub struct OraclePriceResponse {
+    pub price: Decimal,
 }
 
 #[cw_serde]
@@ -51,3 +496,34 @@ pub enum SwapType {
     MaxSlippagePercentage(Decimal),
     MinOutputAmount(Uint128),
 }
+
+/// How the hops of a swap route are executed, `Atomic` if not specified.
+#[cw_serde]
+pub enum ExecutionMode {
+    /// All hops run in a single swap message bound by the minimum output of the whole swap.
+    Atomic,
+    /// Every hop runs in its own swap message, bound by a minimum output derived from
+    /// the price of that hop minus hop_max_slippage_percentage.
+    StepWise {
+        hop_max_slippage_percentage: Decimal,
+    },
+    /// Like `Atomic`, but a failed swap refunds the input to the sender and
+    /// emits a `swap_failed` event instead of reverting the transaction.
+    BestEffort,
+}
+
+/// Point after which a swap is no longer executed.
+#[cw_serde]
+pub enum Deadline {
+    AtTime(Timestamp),
+    AtHeight(u64),
+}
+
+impl Deadline {
+    pub fn is_passed(&self, block: &BlockInfo) -> bool {
+        match self {
+            Deadline::AtTime(time) => block.time > *time,
+            Deadline::AtHeight(height) => block.height > *height,
+        }
+    }
+}
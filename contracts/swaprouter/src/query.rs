@@ -1,10 +1,34 @@
-use cosmwasm_std::{Deps, StdResult};
+use cosmwasm_std::{Coin, Decimal, Deps, Env, Order, StdError, StdResult, Uint128, Uint256};
+use cw_storage_plus::Bound;
+use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountInRoute;
 
 use crate::{
-    msg::{GetOwnerResponse, GetRouteResponse},
-    state::{OWNER, ROUTING_TABLE},
+    helpers::{
+        cumulative_price_at, latest_price_checkpoint, load_route, remaining_rate_limit,
+        resolve_denom, resolve_pool_route, route_rejection_reason, to_swap_route,
+    },
+    msg::{
+        AllPairStatsResponse, DenomAliasEntry, DenomAliasesResponse, DenomList, DenomListResponse,
+        DenomPair, DisplayAmountResponse, ExportRoutesResponse, GetConfigResponse,
+        GetFallbackRoutesResponse, GetOwnerResponse, GetPairConfigResponse, GetRouteResponse,
+        ListRoutesEntry, ListRoutesResponse, PairStatsEntry, PairStatsResponse, ProposedRouteEntry,
+        ProposedRoutesResponse, RemainingCapacityResponse, RouteDependentsResponse, RouteEntry,
+        RouteHealthEntry, RouteHealthResponse, RouteHistoryEntry, RouteHistoryResponse,
+        RouterTwapResponse, RoutesByPoolResponse, SwapHistoryEntry, SwapHistoryResponse,
+        ValidateRouteResponse,
+    },
+    state::{
+        swap_history, ALLOWED_DENOMS, BLOCKED_DENOMS, CONFIG, DENOM_ALIASES, DENOM_SYMBOLS,
+        FALLBACK_ROUTES, OWNER, PAIR_CONFIGS, PAIR_STATS, POOL_ROUTES, PROPOSED_ROUTES,
+        RATE_LIMITS, ROUTE_DEPENDENTS, ROUTE_EXPIRATIONS, ROUTE_HISTORY, ROUTING_TABLE,
+        SUSPENDED_ROUTES,
+    },
 };
 
+// settings for pagination
+pub const DEFAULT_LIMIT: u32 = 10;
+pub const MAX_LIMIT: u32 = 30;
+
 // query_owner returns contracr owner. Returns error on storage failure.
 pub fn query_owner(deps: Deps) -> StdResult<GetOwnerResponse> {
     let owner = OWNER.load(deps.storage)?;
@@ -14,13 +38,510 @@ pub fn query_owner(deps: Deps) -> StdResult<GetOwnerResponse> {
 }
 
 // query_route returns query route for given
-// input and output denoms.
-// Returns error on any storage failure.
+// input and output denoms, or the symbols of their aliases.
+// Returns error if the route does not exist or has expired, or on any storage failure.
 pub fn query_route(
     deps: Deps,
+    env: Env,
     input_denom: String,
     output_denom: String,
 ) -> StdResult<GetRouteResponse> {
-    let route = ROUTING_TABLE.load(deps.storage, (&input_denom, &output_denom))?;
+    let input_denom = resolve_denom(deps.storage, &input_denom)?;
+    let output_denom = resolve_denom(deps.storage, &output_denom)?;
+    let route = load_route(deps.storage, &input_denom, &output_denom, env.block.time)?;
     Ok(GetRouteResponse { pool_route: route })
 }
+
+// query_validate_route dry-runs the validation of a route from input_denom
+// to output_denom, as done when the route is set.
+// Denoms may be given as the symbols of denom aliases.
+// Returns the reason the route is rejected in the response rather than as error.
+pub fn query_validate_route(
+    deps: Deps,
+    input_denom: String,
+    output_denom: String,
+    pool_route: Vec<SwapAmountInRoute>,
+) -> StdResult<ValidateRouteResponse> {
+    let input_denom = resolve_denom(deps.storage, &input_denom)?;
+    let output_denom = resolve_denom(deps.storage, &output_denom)?;
+    let pool_route = resolve_pool_route(deps.storage, pool_route)?;
+    // routes that can not be validated are reported with the validation error.
+    let reason = route_rejection_reason(deps, input_denom, output_denom, pool_route)
+        .unwrap_or_else(|err| Some(err.to_string()));
+    Ok(ValidateRouteResponse {
+        valid: reason.is_none(),
+        reason,
+    })
+}
+
+// query_route_history returns the changes of the route from input_denom to output_denom,
+// or the denoms of their aliases, in ascending version order, starting after the given version.
+pub fn query_route_history(
+    deps: Deps,
+    input_denom: String,
+    output_denom: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<RouteHistoryResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let input_denom = resolve_denom(deps.storage, &input_denom)?;
+    let output_denom = resolve_denom(deps.storage, &output_denom)?;
+
+    let changes = ROUTE_HISTORY
+        .prefix((&input_denom, &output_denom))
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(version, change)| RouteHistoryEntry { version, change }))
+        .collect::<StdResult<_>>()?;
+
+    Ok(RouteHistoryResponse { changes })
+}
+
+// query_routes_by_pool returns the (input_denom, output_denom) pairs whose route
+// swaps through the given pool. Returns error on any storage failure.
+pub fn query_routes_by_pool(deps: Deps, pool_id: u64) -> StdResult<RoutesByPoolResponse> {
+    let routes = POOL_ROUTES
+        .sub_prefix(pool_id)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            item.map(|(input_denom, output_denom)| DenomPair {
+                input_denom,
+                output_denom,
+            })
+        })
+        .collect::<StdResult<_>>()?;
+
+    Ok(RoutesByPoolResponse { routes })
+}
+
+// query_route_dependents returns the pairs whose route is composed of the route
+// from input_denom to output_denom, or the denoms of their aliases, empty if there are none.
+// Returns error on any storage failure.
+pub fn query_route_dependents(
+    deps: Deps,
+    input_denom: String,
+    output_denom: String,
+) -> StdResult<RouteDependentsResponse> {
+    let input_denom = resolve_denom(deps.storage, &input_denom)?;
+    let output_denom = resolve_denom(deps.storage, &output_denom)?;
+    let routes = ROUTE_DEPENDENTS
+        .may_load(deps.storage, (&input_denom, &output_denom))?
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(input_denom, output_denom)| DenomPair {
+            input_denom,
+            output_denom,
+        })
+        .collect();
+    Ok(RouteDependentsResponse { routes })
+}
+
+// query_route_health re-validates the stored routes ordered by
+// (input_denom, output_denom), starting after the given pair.
+// Returns the status of every route, including the reason unhealthy routes are rejected.
+pub fn query_route_health(
+    deps: Deps,
+    start_after: Option<(String, String)>,
+    limit: Option<u32>,
+) -> StdResult<RouteHealthResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_ref().map(|(input_denom, output_denom)| {
+        Bound::exclusive((input_denom.as_str(), output_denom.as_str()))
+    });
+
+    let routes = ROUTING_TABLE
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            item.map(|((input_denom, output_denom), route)| {
+                let reason = route_rejection_reason(
+                    deps,
+                    input_denom.clone(),
+                    output_denom.clone(),
+                    to_swap_route(route),
+                )
+                .unwrap_or_else(|err| Some(err.to_string()));
+                let suspended = SUSPENDED_ROUTES.has(deps.storage, (&input_denom, &output_denom));
+                RouteHealthEntry {
+                    input_denom,
+                    output_denom,
+                    healthy: reason.is_none(),
+                    reason,
+                    suspended,
+                }
+            })
+        })
+        .collect::<StdResult<_>>()?;
+
+    Ok(RouteHealthResponse { routes })
+}
+
+// query_fallback_routes returns the ordered fallback routes for given
+// input and output denoms, or the symbols of their aliases, empty if none were set.
+// Returns error on any storage failure.
+pub fn query_fallback_routes(
+    deps: Deps,
+    input_denom: String,
+    output_denom: String,
+) -> StdResult<GetFallbackRoutesResponse> {
+    let input_denom = resolve_denom(deps.storage, &input_denom)?;
+    let output_denom = resolve_denom(deps.storage, &output_denom)?;
+    let fallback_routes = FALLBACK_ROUTES
+        .may_load(deps.storage, (&input_denom, &output_denom))?
+        .unwrap_or_default();
+    Ok(GetFallbackRoutesResponse { fallback_routes })
+}
+
+// query_proposed_routes returns the pending route proposals ordered by
+// (input_denom, output_denom), starting after the given pair.
+// Returns error on any storage failure.
+pub fn query_proposed_routes(
+    deps: Deps,
+    start_after: Option<(String, String)>,
+    limit: Option<u32>,
+) -> StdResult<ProposedRoutesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_ref().map(|(input_denom, output_denom)| {
+        Bound::exclusive((input_denom.as_str(), output_denom.as_str()))
+    });
+
+    let route_timelock_seconds = CONFIG.load(deps.storage)?.route_timelock_seconds;
+    let proposals = PROPOSED_ROUTES
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            item.map(
+                |((input_denom, output_denom), proposal)| ProposedRouteEntry {
+                    input_denom,
+                    output_denom,
+                    executable_after: proposal.proposed_at.plus_seconds(route_timelock_seconds),
+                    proposal,
+                },
+            )
+        })
+        .collect::<StdResult<_>>()?;
+
+    Ok(ProposedRoutesResponse { proposals })
+}
+
+// query_list_routes returns the stored routes ordered by (input_denom, output_denom),
+// starting after the given pair, flagging the ones that have expired.
+// Returns error on any storage failure.
+pub fn query_list_routes(
+    deps: Deps,
+    env: Env,
+    start_after: Option<(String, String)>,
+    limit: Option<u32>,
+) -> StdResult<ListRoutesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_ref().map(|(input_denom, output_denom)| {
+        Bound::exclusive((input_denom.as_str(), output_denom.as_str()))
+    });
+
+    let routes = ROUTING_TABLE
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let ((input_denom, output_denom), pool_route) = item?;
+            let expires_at =
+                ROUTE_EXPIRATIONS.may_load(deps.storage, (&input_denom, &output_denom))?;
+            Ok(ListRoutesEntry {
+                input_denom,
+                output_denom,
+                pool_route,
+                expires_at,
+                expired: matches!(expires_at, Some(expires_at) if expires_at <= env.block.time),
+            })
+        })
+        .collect::<StdResult<_>>()?;
+
+    Ok(ListRoutesResponse { routes })
+}
+
+// query_export_routes returns every unexpired route in the format accepted by `SetRoutes`,
+// ordered by (input_denom, output_denom).
+// Expired routes are left out, as `SetRoutes` rejects expirations in the past,
+// and so are suspended routes, as `SetRoutes` would lift their suspension.
+// Returns error on any storage failure.
+pub fn query_export_routes(deps: Deps, env: Env) -> StdResult<ExportRoutesResponse> {
+    let mut routes = vec![];
+    for item in ROUTING_TABLE.range(deps.storage, None, None, Order::Ascending) {
+        let ((input_denom, output_denom), route) = item?;
+        let expires_at = ROUTE_EXPIRATIONS.may_load(deps.storage, (&input_denom, &output_denom))?;
+        if matches!(expires_at, Some(expires_at) if expires_at <= env.block.time)
+            || SUSPENDED_ROUTES.has(deps.storage, (&input_denom, &output_denom))
+        {
+            continue;
+        }
+        routes.push(RouteEntry {
+            input_denom,
+            output_denom,
+            pool_route: to_swap_route(route),
+            expires_at,
+        });
+    }
+
+    Ok(ExportRoutesResponse { routes })
+}
+
+// query_remaining_capacity returns the rate limit of denom, or of the denom of its alias,
+// and the input amount that can still be swapped in its current window,
+// in total and by the sender if given.
+// Returns error if the sender address is invalid.
+pub fn query_remaining_capacity(
+    deps: Deps,
+    env: Env,
+    denom: String,
+    sender: Option<String>,
+) -> StdResult<RemainingCapacityResponse> {
+    let denom = resolve_denom(deps.storage, &denom)?;
+    let sender = sender
+        .map(|sender| deps.api.addr_validate(&sender))
+        .transpose()?;
+
+    let (remaining, sender_remaining) =
+        remaining_rate_limit(deps, &denom, sender.as_ref(), env.block.time)?;
+    Ok(RemainingCapacityResponse {
+        rate_limit: RATE_LIMITS.may_load(deps.storage, &denom)?,
+        remaining,
+        sender_remaining,
+    })
+}
+
+// query_denom_list returns the denoms in the allowlist or the blocklist ordered by denom,
+// starting after the given denom.
+// Returns error on any storage failure.
+pub fn query_denom_list(
+    deps: Deps,
+    list: DenomList,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<DenomListResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_deref().map(Bound::exclusive);
+
+    let denoms = match list {
+        DenomList::Allowlist => ALLOWED_DENOMS,
+        DenomList::Blocklist => BLOCKED_DENOMS,
+    };
+    let denoms = denoms
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<_>>()?;
+
+    Ok(DenomListResponse { denoms })
+}
+
+// query_denom_aliases returns the denom aliases ordered by symbol, starting after the given symbol.
+// Returns error on any storage failure.
+pub fn query_denom_aliases(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<DenomAliasesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_deref().map(Bound::exclusive);
+
+    let aliases = DENOM_ALIASES
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(symbol, alias)| DenomAliasEntry { symbol, alias }))
+        .collect::<StdResult<_>>()?;
+
+    Ok(DenomAliasesResponse { aliases })
+}
+
+// query_display_amount converts the amount of coin, given by denom or symbol,
+// to the display unit of its alias.
+// Returns error if the denom has no alias.
+pub fn query_display_amount(deps: Deps, coin: Coin) -> StdResult<DisplayAmountResponse> {
+    let denom = resolve_denom(deps.storage, &coin.denom)?;
+    let symbol = DENOM_SYMBOLS
+        .may_load(deps.storage, &denom)?
+        .ok_or_else(|| StdError::generic_err(format!("denom {denom} has no alias")))?;
+    let alias = DENOM_ALIASES.load(deps.storage, &symbol)?;
+
+    let amount = Decimal::from_atomics(coin.amount, alias.exponent)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+    Ok(DisplayAmountResponse {
+        amount,
+        display: alias.display,
+    })
+}
+
+// query_config returns the contract settings. Returns error on storage failure.
+pub fn query_config(deps: Deps) -> StdResult<GetConfigResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    Ok(GetConfigResponse { config })
+}
+
+// query_pair_config returns the pricing settings and swap limits for given
+// input and output denoms, or the symbols of their aliases, or the defaults if none were set.
+// Returns error on any storage failure.
+pub fn query_pair_config(
+    deps: Deps,
+    input_denom: String,
+    output_denom: String,
+) -> StdResult<GetPairConfigResponse> {
+    let input_denom = resolve_denom(deps.storage, &input_denom)?;
+    let output_denom = resolve_denom(deps.storage, &output_denom)?;
+    let pair_config = PAIR_CONFIGS
+        .may_load(deps.storage, (&input_denom, &output_denom))?
+        .unwrap_or_default();
+    Ok(GetPairConfigResponse { pair_config })
+}
+
+// query_swap_history returns recorded swaps in ascending id order,
+// starting after the given id.
+pub fn query_swap_history(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<SwapHistoryResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let records = swap_history()
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(id, record)| SwapHistoryEntry { id, record }))
+        .collect::<StdResult<_>>()?;
+
+    Ok(SwapHistoryResponse { records })
+}
+
+// query_swap_history_by_sender returns recorded swaps initiated by the given sender
+// in ascending id order, starting after the given id.
+// Returns error if the sender address is invalid.
+pub fn query_swap_history_by_sender(
+    deps: Deps,
+    sender: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<SwapHistoryResponse> {
+    let sender = deps.api.addr_validate(&sender)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let records = swap_history()
+        .idx
+        .sender
+        .prefix(sender)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(id, record)| SwapHistoryEntry { id, record }))
+        .collect::<StdResult<_>>()?;
+
+    Ok(SwapHistoryResponse { records })
+}
+
+// query_swap_history_by_pair returns recorded swaps from input_denom to output_denom,
+// or the denoms of their aliases, in ascending id order, starting after the given id.
+pub fn query_swap_history_by_pair(
+    deps: Deps,
+    input_denom: String,
+    output_denom: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<SwapHistoryResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let input_denom = resolve_denom(deps.storage, &input_denom)?;
+    let output_denom = resolve_denom(deps.storage, &output_denom)?;
+
+    let records = swap_history()
+        .idx
+        .pair
+        .prefix((input_denom, output_denom))
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(id, record)| SwapHistoryEntry { id, record }))
+        .collect::<StdResult<_>>()?;
+
+    Ok(SwapHistoryResponse { records })
+}
+
+// query_pair_stats returns the cumulative swap statistics for given
+// input and output denoms, or the symbols of their aliases.
+// Returns error if no swap has been completed for the pair.
+pub fn query_pair_stats(
+    deps: Deps,
+    input_denom: String,
+    output_denom: String,
+) -> StdResult<PairStatsResponse> {
+    let input_denom = resolve_denom(deps.storage, &input_denom)?;
+    let output_denom = resolve_denom(deps.storage, &output_denom)?;
+    let stats = PAIR_STATS.load(deps.storage, (&input_denom, &output_denom))?;
+    Ok(PairStatsResponse { stats })
+}
+
+// query_all_pair_stats returns the cumulative swap statistics of all pairs
+// ordered by (input_denom, output_denom), starting after the given pair.
+pub fn query_all_pair_stats(
+    deps: Deps,
+    start_after: Option<(String, String)>,
+    limit: Option<u32>,
+) -> StdResult<AllPairStatsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_ref().map(|(input_denom, output_denom)| {
+        Bound::exclusive((input_denom.as_str(), output_denom.as_str()))
+    });
+
+    let pairs = PAIR_STATS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            item.map(|((input_denom, output_denom), stats)| PairStatsEntry {
+                input_denom,
+                output_denom,
+                stats,
+            })
+        })
+        .collect::<StdResult<_>>()?;
+
+    Ok(AllPairStatsResponse { pairs })
+}
+
+// query_router_twap returns the time-weighted average execution price of the
+// router's own swaps from input_denom to output_denom, or the denoms of their aliases,
+// over the last window_seconds.
+// Returns error if the pair has no price checkpoint old enough to cover the window.
+pub fn query_router_twap(
+    deps: Deps,
+    env: Env,
+    input_denom: String,
+    output_denom: String,
+    window_seconds: u64,
+) -> StdResult<RouterTwapResponse> {
+    if window_seconds == 0 {
+        return Err(StdError::generic_err("window_seconds must be positive"));
+    }
+    let input_denom = resolve_denom(deps.storage, &input_denom)?;
+    let output_denom = resolve_denom(deps.storage, &output_denom)?;
+    let pair = (input_denom.as_str(), output_denom.as_str());
+    let end_time = env.block.time.seconds();
+    let start_time = end_time.checked_sub(window_seconds).ok_or_else(|| {
+        StdError::generic_err(format!("window of {window_seconds}s exceeds block time"))
+    })?;
+
+    let (checkpoint_time, checkpoint) = latest_price_checkpoint(deps.storage, pair, start_time)?
+        .ok_or_else(|| {
+            StdError::generic_err(format!(
+                "not enough price history for {input_denom} -> {output_denom} over {window_seconds}s"
+            ))
+        })?;
+    let start = cumulative_price_at(checkpoint_time, &checkpoint, start_time)?;
+
+    // the start checkpoint exists, so there is always a checkpoint at or before the end time.
+    let (checkpoint_time, checkpoint) =
+        latest_price_checkpoint(deps.storage, pair, end_time)?.unwrap();
+    let end = cumulative_price_at(checkpoint_time, &checkpoint, end_time)?;
+
+    let average = (end - start) / Uint256::from(window_seconds);
+    let average = Uint128::try_from(average).map_err(|e| StdError::generic_err(e.to_string()))?;
+    let twap = Decimal::from_atomics(average, checkpoint.price.decimal_places())
+        .map_err(|e| StdError::generic_err(e.to_string()))?;
+
+    Ok(RouterTwapResponse { twap })
+}
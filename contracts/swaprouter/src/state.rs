@@ -2,19 +2,314 @@
 // see: https://crates.io/crates/cw-storage-plus
 
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::Addr;
-use cw_storage_plus::{Item, Map};
-use osmosis_std::types::osmosis::gamm::v1beta1::{MsgSwapExactAmountIn, SwapAmountInRoute};
+use cosmwasm_std::{Addr, Coin, Decimal, Empty, Timestamp, Uint128, Uint256};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
+use osmosis_std::types::osmosis::poolmanager::v1beta1::{MsgSwapExactAmountIn, SwapAmountInRoute};
+
+use crate::msg::SwapType;
+
+#[cw_serde]
+pub struct Config {
+    // maximum number of swap records kept in the swap history.
+    // Oldest records are pruned first; 0 disables the history.
+    pub swap_history_retention: u64,
+    // longest window, in seconds, the router TWAP can be queried for.
+    // Older price checkpoints are pruned.
+    pub router_twap_max_window: u64,
+    // maximum deviation of the spot price from the TWAP along the route,
+    // in percent of the TWAP, for swaps priced by TWAP; 0 disables the check.
+    pub max_spot_twap_deviation_percentage: Decimal,
+    // maximum number of hops in a route; 0 disables the check.
+    pub max_route_hops: u64,
+    // minimum amount of both the swapped in and swapped out denom
+    // every pool in a route must hold; 0 disables the check.
+    pub min_pool_liquidity: Uint128,
+    // delay, in seconds, between proposing a route and executing it;
+    // 0 lets the owner set routes directly.
+    pub route_timelock_seconds: u64,
+    // whether only allowlisted denoms may be swapped; the blocklist always applies.
+    pub denom_allowlist_enabled: bool,
+}
+
+// PoolType is the poolmanager pool type of a pool in a route.
+#[cw_serde]
+pub enum PoolType {
+    Balancer,
+    Stableswap,
+    Concentrated,
+    CosmWasm,
+}
+
+#[cw_serde]
+pub struct RouteHop {
+    pub pool_id: u64,
+    pub pool_type: PoolType,
+    pub token_out_denom: String,
+}
+
+impl From<RouteHop> for SwapAmountInRoute {
+    fn from(hop: RouteHop) -> Self {
+        SwapAmountInRoute {
+            pool_id: hop.pool_id,
+            token_out_denom: hop.token_out_denom,
+        }
+    }
+}
+
+// RouteSegment is a part of a route given to `SetRoute`,
+// either a raw hop or the stored route of another pair.
+#[cw_serde]
+#[serde(untagged)]
+pub enum RouteSegment {
+    Route {
+        input_denom: String,
+        output_denom: String,
+    },
+    Hop(SwapAmountInRoute),
+}
+
+#[cw_serde]
+pub struct RouteChange {
+    pub changed_by: Addr,
+    pub block_height: u64,
+    pub time: Timestamp,
+    // route before the change, empty if the pair had no route.
+    pub previous_route: Vec<RouteHop>,
+    // route after the change, empty if the route was removed.
+    pub new_route: Vec<RouteHop>,
+}
+
+#[cw_serde]
+pub struct ProposedRoute {
+    pub proposed_by: Addr,
+    pub pool_route: Vec<SwapAmountInRoute>,
+    pub bidirectional: bool,
+    pub expires_at: Option<Timestamp>,
+    // re-expand the routes composed of the route on execution.
+    #[serde(default)]
+    pub propagate: bool,
+    // time the route was proposed; it can be executed once the current
+    // route timelock has elapsed since then.
+    pub proposed_at: Timestamp,
+}
+
+// DenomAlias is the on-chain denom a symbol such as "ATOM" stands for.
+#[cw_serde]
+pub struct DenomAlias {
+    pub denom: String,
+    // number of decimal places between the denom and its display unit.
+    pub exponent: u32,
+    // name of the display unit, such as "atom".
+    pub display: String,
+}
+
+#[cw_serde]
+pub enum RateLimitWindow {
+    // rolling window of the given number of seconds, tracked in fixed buckets
+    // of a tenth of the window each.
+    Seconds(u64),
+    // current epoch of the Osmosis epochs module with the given identifier, such as "day".
+    Epoch { identifier: String },
+}
+
+#[cw_serde]
+pub struct RateLimit {
+    pub window: RateLimitWindow,
+    // maximum input amount of the denom swapped through the router per window.
+    pub max_volume: Uint128,
+    // maximum input amount of the denom each sender may swap per window;
+    // senders are not limited separately if not set.
+    pub max_volume_per_sender: Option<Uint128>,
+}
+
+#[cw_serde]
+pub struct TwapWindows {
+    pub short_window_seconds: u64,
+    pub long_window_seconds: u64,
+    // maximum divergence of the short window TWAP from the long window TWAP,
+    // in percent of the long window TWAP.
+    pub max_divergence_percentage: Decimal,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub enum PriceSource {
+    // arithmetic TWAP of the route pools from the Osmosis TWAP module.
+    #[default]
+    ArithmeticTwap,
+    // geometric TWAP of the route pools from the Osmosis TWAP module.
+    GeometricTwap,
+    // external oracle contract implementing `OracleQueryMsg`.
+    Oracle {
+        contract_address: Addr,
+    },
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct PairConfig {
+    // source of the price used for swaps with max slippage percentage.
+    #[serde(default)]
+    pub price_source: PriceSource,
+    // TWAP windows used to price swaps with max slippage percentage.
+    // A single 1 second window is used if not set.
+    pub twap_windows: Option<TwapWindows>,
+    // maximum slippage percentage swaps with max slippage percentage may request;
+    // swaps with min output amount may not request less than the output it allows.
+    pub max_slippage_percentage: Option<Decimal>,
+    // minimum input amount of a swap.
+    pub min_input_amount: Option<Uint128>,
+    // maximum input amount of a swap, in percent of the input denom
+    // liquidity of the first pool in the route.
+    pub max_input_liquidity_percentage: Option<Decimal>,
+}
+
+#[cw_serde]
+pub struct StepWiseState {
+    // full route of the swap, the swap message only holds the current hop.
+    pub route: Vec<SwapAmountInRoute>,
+    // index of the current hop in the route.
+    pub hop: usize,
+    pub input_coin: Coin,
+    // minimum output of the whole swap, enforced on the last hop.
+    pub min_output: Coin,
+    pub hop_max_slippage_percentage: Decimal,
+}
 
 #[cw_serde]
 pub struct SwapMsgReplyState {
     pub original_sender: Addr,
     pub swap_msg: MsgSwapExactAmountIn,
+    pub swap_type: SwapType,
+    // set when the swap is executed hop by hop.
+    pub step_wise: Option<StepWiseState>,
+    // set when a failed swap refunds the input instead of reverting.
+    pub best_effort: bool,
+    // fallback routes not yet tried, in the order they are tried.
+    pub fallback_routes: Vec<Vec<SwapAmountInRoute>>,
+    // failure reasons of the routes tried so far.
+    pub failures: Vec<String>,
+}
+
+#[cw_serde]
+pub struct SwapRecord {
+    pub sender: Addr,
+    pub input_coin: Coin,
+    pub output_coin: Coin,
+    pub pool_ids: Vec<u64>,
+    pub block_height: u64,
+    pub time: Timestamp,
+    pub swap_type: SwapType,
+}
+
+#[cw_serde]
+pub struct PairStats {
+    pub total_input: Uint128,
+    pub total_output: Uint128,
+    pub swap_count: u64,
+    // amount of output received per unit of input in the last swap.
+    pub last_execution_price: Decimal,
+    pub last_timestamp: Timestamp,
+}
+
+#[cw_serde]
+pub struct PriceCheckpoint {
+    // execution price atomics multiplied by the seconds each price was in effect,
+    // summed up to the time of this checkpoint.
+    pub cumulative_price: Uint256,
+    // execution price of the last swap at the time of this checkpoint.
+    pub price: Decimal,
+}
+
+pub struct SwapHistoryIndexes<'a> {
+    pub sender: MultiIndex<'a, Addr, SwapRecord, u64>,
+    pub pair: MultiIndex<'a, (String, String), SwapRecord, u64>,
+}
+
+impl<'a> IndexList<SwapRecord> for SwapHistoryIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<SwapRecord>> + '_> {
+        let v: Vec<&dyn Index<SwapRecord>> = vec![&self.sender, &self.pair];
+        Box::new(v.into_iter())
+    }
+}
+
+// swap_history stores completed swaps keyed by a sequential id,
+// indexed by sender and by (input_denom, output_denom).
+pub fn swap_history<'a>() -> IndexedMap<'a, u64, SwapRecord, SwapHistoryIndexes<'a>> {
+    let indexes = SwapHistoryIndexes {
+        sender: MultiIndex::new(
+            |record| record.sender.clone(),
+            "swap_history",
+            "swap_history__sender",
+        ),
+        pair: MultiIndex::new(
+            |record| {
+                (
+                    record.input_coin.denom.clone(),
+                    record.output_coin.denom.clone(),
+                )
+            },
+            "swap_history",
+            "swap_history__pair",
+        ),
+    };
+    IndexedMap::new("swap_history", indexes)
 }
 
 // OWNER stores the contract owner configured at instantiation time.
 pub const OWNER: Item<Addr> = Item::new("owner");
+// CONFIG stores owner-adjustable contract settings.
+pub const CONFIG: Item<Config> = Item::new("config");
 // ROUTING_TABLE stores the swap route set by the owner.
-pub const ROUTING_TABLE: Map<(&str, &str), Vec<SwapAmountInRoute>> = Map::new("routing_table");
+pub const ROUTING_TABLE: Map<(&str, &str), Vec<RouteHop>> = Map::new("routing_table");
+// ROUTE_EXPIRATIONS stores the time after which the route in
+// ROUTING_TABLE is treated as missing, for routes set with an expiration.
+pub const ROUTE_EXPIRATIONS: Map<(&str, &str), Timestamp> = Map::new("route_expirations");
+// SUSPENDED_ROUTES stores the reason the route in ROUTING_TABLE failed validation,
+// for routes suspended by `PruneInvalidRoutes`. Suspended routes are treated as missing.
+pub const SUSPENDED_ROUTES: Map<(&str, &str), String> = Map::new("suspended_routes");
+// ROUTE_COMPOSITIONS stores the segments of the routes in ROUTING_TABLE
+// that reference the stored routes of other pairs.
+pub const ROUTE_COMPOSITIONS: Map<(&str, &str), Vec<RouteSegment>> = Map::new("route_compositions");
+// ROUTE_DEPENDENTS stores the (input_denom, output_denom) pairs whose route
+// in ROUTE_COMPOSITIONS references the route of the pair.
+pub const ROUTE_DEPENDENTS: Map<(&str, &str), Vec<(String, String)>> = Map::new("route_dependents");
+// POOL_ROUTES indexes the ROUTING_TABLE entries by the pools their route uses,
+// keyed by (pool_id, input_denom, output_denom).
+pub const POOL_ROUTES: Map<(u64, &str, &str), Empty> = Map::new("pool_routes");
+// ROUTE_HISTORY stores the changes of the route per
+// (input_denom, output_denom, version), versions start at 1.
+pub const ROUTE_HISTORY: Map<(&str, &str, u64), RouteChange> = Map::new("route_history");
+// FALLBACK_ROUTES stores the ordered routes tried when the route in
+// ROUTING_TABLE fails, set by the owner.
+pub const FALLBACK_ROUTES: Map<(&str, &str), Vec<Vec<RouteHop>>> = Map::new("fallback_routes");
+// PROPOSED_ROUTES stores the route changes proposed by the owner
+// while they wait for the route timelock, per (input_denom, output_denom).
+pub const PROPOSED_ROUTES: Map<(&str, &str), ProposedRoute> = Map::new("proposed_routes");
+// DENOM_ALIASES stores the denom aliases per symbol set by the owner.
+pub const DENOM_ALIASES: Map<&str, DenomAlias> = Map::new("denom_aliases");
+// DENOM_SYMBOLS indexes DENOM_ALIASES by the aliased denom.
+pub const DENOM_SYMBOLS: Map<&str, String> = Map::new("denom_symbols");
+// ALLOWED_DENOMS stores the denoms the router may swap when the allowlist is enabled.
+pub const ALLOWED_DENOMS: Map<&str, Empty> = Map::new("allowed_denoms");
+// BLOCKED_DENOMS stores the denoms the router must never swap.
+pub const BLOCKED_DENOMS: Map<&str, Empty> = Map::new("blocked_denoms");
+// RATE_LIMITS stores the swap volume limits per input denom set by the owner.
+pub const RATE_LIMITS: Map<&str, RateLimit> = Map::new("rate_limits");
+// RATE_LIMIT_VOLUMES stores the input amount swapped per (denom, window key, sender),
+// the window key being the bucket of the time or the epoch number of the swap.
+// The volume of all senders is kept under the empty sender.
+pub const RATE_LIMIT_VOLUMES: Map<(&str, u64, &str), Uint128> = Map::new("rate_limit_volumes");
+// PAIR_CONFIGS stores the pricing settings and swap limits per (input_denom, output_denom)
+// set by the owner.
+pub const PAIR_CONFIGS: Map<(&str, &str), PairConfig> = Map::new("pair_configs");
 // SWAP_REPLY_STATES persists data from swap message creation until the reply receipt.
 pub const SWAP_REPLY_STATES: Map<u64, SwapMsgReplyState> = Map::new("swap_reply_states");
+// SWAP_HISTORY_NEXT_ID stores the id assigned to the next swap history record.
+pub const SWAP_HISTORY_NEXT_ID: Item<u64> = Item::new("swap_history_next_id");
+// PAIR_STATS stores cumulative swap statistics per (input_denom, output_denom).
+pub const PAIR_STATS: Map<(&str, &str), PairStats> = Map::new("pair_stats");
+// PRICE_CHECKPOINTS stores the execution price accumulator per
+// (input_denom, output_denom, timestamp in seconds).
+pub const PRICE_CHECKPOINTS: Map<(&str, &str, u64), PriceCheckpoint> =
+    Map::new("price_checkpoints");
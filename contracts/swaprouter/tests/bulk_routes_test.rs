@@ -0,0 +1,73 @@
+mod test_env;
+
+use osmosis_test_tube::{Module, RunnerError, Wasm};
+use swaprouter::msg::{ExecuteMsg, ExportRoutesResponse, QueryMsg, RouteEntry};
+use test_env::*;
+
+fn export_routes(env: &TestEnv) -> Vec<RouteEntry> {
+    Wasm::new(&env.app)
+        .query::<QueryMsg, ExportRoutesResponse>(&env.contract_address, &QueryMsg::ExportRoutes {})
+        .unwrap()
+        .routes
+}
+
+#[test]
+fn exported_routes_can_be_imported_into_another_deployment() {
+    let env = TestEnv::new();
+    let routes = vec![
+        route_entry("stake", "uion", &[(3, "uion")]),
+        route_entry("uion", "uosmo", &[(1, "uosmo")]),
+        route_entry("uosmo", "uion", &[(2, "stake"), (3, "uion")]),
+    ];
+    Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::SetRoutes {
+                routes: routes.clone(),
+            },
+            &[],
+            &env.owner,
+        )
+        .unwrap();
+
+    let exported = export_routes(&env);
+    assert_eq!(exported, routes);
+
+    let clone = TestEnv::new();
+    Wasm::new(&clone.app)
+        .execute(
+            &clone.contract_address,
+            &ExecuteMsg::SetRoutes { routes: exported },
+            &[],
+            &clone.owner,
+        )
+        .unwrap();
+    assert_eq!(export_routes(&clone), routes);
+}
+
+#[test]
+fn set_routes_saves_nothing_if_any_route_is_invalid() {
+    let env = TestEnv::new();
+
+    let err = Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::SetRoutes {
+                routes: vec![
+                    route_entry("uosmo", "uion", &[(1, "uion")]),
+                    route_entry("stake", "uion", &[(1, "uion")]),
+                ],
+            },
+            &[],
+            &env.owner,
+        )
+        .unwrap_err();
+
+    assert_eq!(
+        err,
+        RunnerError::ExecuteError {
+            msg: r#"failed to execute message; message index: 0: Invalid Pool Route: "denom stake is not in pool id 1": execute wasm contract failed"#.to_string()
+        }
+    );
+    assert_eq!(export_routes(&env), vec![]);
+}
@@ -0,0 +1,404 @@
+mod test_env;
+
+use std::str::FromStr;
+
+use cosmwasm_std::{Coin, Decimal, Uint128};
+use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountInRoute;
+use osmosis_test_tube::{Module, RunnerError, Wasm};
+use swaprouter::msg::{
+    DenomAliasesResponse, DenomList, DisplayAmountResponse, ExecuteMsg, GetFallbackRoutesResponse,
+    GetPairConfigResponse, GetRouteResponse, PairStatsResponse, QueryMsg, RouteHistoryResponse,
+    SwapHistoryResponse, SwapType, ValidateRouteResponse,
+};
+use swaprouter::state::{DenomAlias, PairConfig, RateLimit, RateLimitWindow, RouteSegment};
+use test_env::*;
+
+fn set_denom_alias(env: &TestEnv, symbol: &str, denom: &str, exponent: u32) {
+    Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::SetDenomAlias {
+                symbol: symbol.to_string(),
+                alias: DenomAlias {
+                    denom: denom.to_string(),
+                    exponent,
+                    display: symbol.to_lowercase(),
+                },
+            },
+            &[],
+            &env.owner,
+        )
+        .unwrap();
+}
+
+// setup_aliases sets the OSMO and ION aliases and the OSMO -> ION route through pool(1).
+fn setup_aliases(env: &TestEnv) {
+    set_denom_alias(env, "OSMO", "uosmo", 6);
+    set_denom_alias(env, "ION", "uion", 6);
+
+    Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::SetRoute {
+                input_denom: "OSMO".to_string(),
+                output_denom: "ION".to_string(),
+                pool_route: vec![RouteSegment::Hop(SwapAmountInRoute {
+                    pool_id: 1,
+                    token_out_denom: "ION".to_string(),
+                })],
+                bidirectional: false,
+                expires_at: None,
+                propagate: false,
+            },
+            &[],
+            &env.owner,
+        )
+        .unwrap();
+}
+
+#[test]
+fn route_accepts_symbols_and_denoms() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    setup_aliases(&env);
+
+    for (input_denom, output_denom) in [("OSMO", "ION"), ("uosmo", "uion"), ("OSMO", "uion")] {
+        let res: GetRouteResponse = wasm
+            .query(
+                &env.contract_address,
+                &QueryMsg::GetRoute {
+                    input_denom: input_denom.to_string(),
+                    output_denom: output_denom.to_string(),
+                },
+            )
+            .unwrap();
+        assert_eq!(res.pool_route.len(), 1);
+        assert_eq!(res.pool_route[0].token_out_denom, "uion");
+    }
+}
+
+#[test]
+fn swap_accepts_symbols() {
+    let env = TestEnv::new();
+    setup_aliases(&env);
+
+    let sender = env
+        .app
+        .init_account(&[Coin::new(1_000_000_000, "uosmo")])
+        .unwrap();
+    Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::Swap {
+                input_coin: Coin::new(1000, "OSMO"),
+                output_denom: "ION".to_string(),
+                swap_type: SwapType::MinOutputAmount(1u128.into()),
+                deadline: None,
+                execution_mode: None,
+            },
+            &[Coin::new(1000, "uosmo")],
+            &sender,
+        )
+        .unwrap();
+
+    let wasm = Wasm::new(&env.app);
+    let res: PairStatsResponse = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::PairStats {
+                input_denom: "OSMO".to_string(),
+                output_denom: "ION".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.stats.total_input, Uint128::new(1000));
+    let res: SwapHistoryResponse = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::SwapHistoryByPair {
+                input_denom: "OSMO".to_string(),
+                output_denom: "ION".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(res.records.len(), 1);
+}
+
+#[test]
+fn route_queries_accept_symbols() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    setup_aliases(&env);
+
+    let res: RouteHistoryResponse = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::RouteHistory {
+                input_denom: "OSMO".to_string(),
+                output_denom: "ION".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(res.changes.len(), 1);
+
+    let res: ValidateRouteResponse = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::ValidateRoute {
+                input_denom: "OSMO".to_string(),
+                output_denom: "ION".to_string(),
+                pool_route: vec![SwapAmountInRoute {
+                    pool_id: 1,
+                    token_out_denom: "ION".to_string(),
+                }],
+            },
+        )
+        .unwrap();
+    assert_eq!(res.reason, None);
+    assert!(res.valid);
+}
+
+#[test]
+fn display_amount_uses_alias_exponent() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    setup_aliases(&env);
+
+    for denom in ["uosmo", "OSMO"] {
+        let res: DisplayAmountResponse = wasm
+            .query(
+                &env.contract_address,
+                &QueryMsg::DisplayAmount {
+                    coin: Coin::new(1_500_000, denom),
+                },
+            )
+            .unwrap();
+        assert_eq!(res.amount, Decimal::from_str("1.5").unwrap());
+        assert_eq!(res.display, "osmo");
+    }
+
+    let res: DenomAliasesResponse = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::DenomAliases {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    let symbols: Vec<&str> = res
+        .aliases
+        .iter()
+        .map(|entry| entry.symbol.as_str())
+        .collect();
+    assert_eq!(symbols, vec!["ION", "OSMO"]);
+}
+
+#[test]
+fn denom_can_not_have_two_symbols() {
+    let env = TestEnv::new();
+    set_denom_alias(&env, "OSMO", "uosmo", 6);
+
+    let err = Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::SetDenomAlias {
+                symbol: "OSMOSIS".to_string(),
+                alias: DenomAlias {
+                    denom: "uosmo".to_string(),
+                    exponent: 6,
+                    display: "osmo".to_string(),
+                },
+            },
+            &[],
+            &env.owner,
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        RunnerError::ExecuteError {
+            msg: r#"failed to execute message; message index: 0: Invalid Denom Alias: "denom uosmo already has the symbol OSMO": execute wasm contract failed"#.to_string()
+        }
+    );
+}
+
+#[test]
+fn symbol_can_not_be_a_denom() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    env.setup_default_route();
+    set_denom_alias(&env, "OSMO", "uosmo", 6);
+
+    // uosmo is aliased, stake is used by the uosmo -> uion route.
+    for symbol in ["uosmo", "stake"] {
+        let err = wasm
+            .execute(
+                &env.contract_address,
+                &ExecuteMsg::SetDenomAlias {
+                    symbol: symbol.to_string(),
+                    alias: DenomAlias {
+                        denom: "uion".to_string(),
+                        exponent: 6,
+                        display: "ion".to_string(),
+                    },
+                },
+                &[],
+                &env.owner,
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            RunnerError::ExecuteError {
+                msg: format!(
+                    r#"failed to execute message; message index: 0: Invalid Denom Alias: "symbol {symbol} is a denom": execute wasm contract failed"#
+                )
+            }
+        );
+    }
+}
+
+#[test]
+fn symbol_can_not_be_a_denom_of_the_routing_settings() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    let execute = |msg: &ExecuteMsg| {
+        wasm.execute(&env.contract_address, msg, &[], &env.owner)
+            .unwrap();
+    };
+    execute(&ExecuteMsg::UpdateDenomList {
+        list: DenomList::Blocklist,
+        add: vec!["ublocked".to_string()],
+        remove: vec![],
+    });
+    execute(&ExecuteMsg::SetRateLimit {
+        denom: "ulimited".to_string(),
+        rate_limit: Some(RateLimit {
+            window: RateLimitWindow::Seconds(60),
+            max_volume: Uint128::new(1000),
+            max_volume_per_sender: None,
+        }),
+    });
+    execute(&ExecuteMsg::SetPairConfig {
+        input_denom: "uconfigured".to_string(),
+        output_denom: "uosmo".to_string(),
+        pair_config: PairConfig::default(),
+    });
+
+    for symbol in ["ublocked", "ulimited", "uconfigured"] {
+        let err = wasm
+            .execute(
+                &env.contract_address,
+                &ExecuteMsg::SetDenomAlias {
+                    symbol: symbol.to_string(),
+                    alias: DenomAlias {
+                        denom: "uion".to_string(),
+                        exponent: 6,
+                        display: "ion".to_string(),
+                    },
+                },
+                &[],
+                &env.owner,
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            RunnerError::ExecuteError {
+                msg: format!(
+                    r#"failed to execute message; message index: 0: Invalid Denom Alias: "symbol {symbol} is a denom": execute wasm contract failed"#
+                )
+            }
+        );
+    }
+}
+
+#[test]
+fn routing_settings_accept_symbols() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    setup_aliases(&env);
+
+    wasm.execute(
+        &env.contract_address,
+        &ExecuteMsg::SetFallbackRoutes {
+            input_denom: "OSMO".to_string(),
+            output_denom: "ION".to_string(),
+            fallback_routes: vec![vec![SwapAmountInRoute {
+                pool_id: 1,
+                token_out_denom: "ION".to_string(),
+            }]],
+        },
+        &[],
+        &env.owner,
+    )
+    .unwrap();
+    wasm.execute(
+        &env.contract_address,
+        &ExecuteMsg::SetPairConfig {
+            input_denom: "OSMO".to_string(),
+            output_denom: "ION".to_string(),
+            pair_config: PairConfig {
+                min_input_amount: Some(Uint128::new(10)),
+                ..Default::default()
+            },
+        },
+        &[],
+        &env.owner,
+    )
+    .unwrap();
+
+    let res: GetFallbackRoutesResponse = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::GetFallbackRoutes {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.fallback_routes[0][0].token_out_denom, "uion");
+    let res: GetPairConfigResponse = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::GetPairConfig {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.pair_config.min_input_amount, Some(Uint128::new(10)));
+}
+
+#[test]
+fn set_denom_alias_by_non_owner() {
+    let env = TestEnv::new();
+    let non_owner = env
+        .app
+        .init_account(&[Coin::new(1_000_000_000_000, "uosmo")])
+        .unwrap();
+
+    let err = Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::SetDenomAlias {
+                symbol: "OSMO".to_string(),
+                alias: DenomAlias {
+                    denom: "uosmo".to_string(),
+                    exponent: 6,
+                    display: "osmo".to_string(),
+                },
+            },
+            &[],
+            &non_owner,
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        RunnerError::ExecuteError {
+            msg: "failed to execute message; message index: 0: Unauthorized: execute wasm contract failed".to_string()
+        }
+    );
+}
@@ -0,0 +1,168 @@
+mod test_env;
+
+use cosmwasm_std::Coin;
+use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountInRoute;
+use osmosis_test_tube::{Module, RunnerError, Wasm};
+use swaprouter::msg::{ConfigUpdate, DenomList, DenomListResponse, ExecuteMsg, QueryMsg, SwapType};
+use swaprouter::state::RouteSegment;
+use test_env::*;
+
+fn update_denom_list(env: &TestEnv, list: DenomList, add: &[&str], remove: &[&str]) {
+    Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::UpdateDenomList {
+                list,
+                add: add.iter().map(|denom| denom.to_string()).collect(),
+                remove: remove.iter().map(|denom| denom.to_string()).collect(),
+            },
+            &[],
+            &env.owner,
+        )
+        .unwrap();
+}
+
+fn enable_allowlist(env: &TestEnv) {
+    Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::UpdateConfig(ConfigUpdate {
+                denom_allowlist_enabled: Some(true),
+                ..Default::default()
+            }),
+            &[],
+            &env.owner,
+        )
+        .unwrap();
+}
+
+fn swap(env: &TestEnv) -> Result<(), RunnerError> {
+    let sender = env
+        .app
+        .init_account(&[Coin::new(1_000_000_000, "uosmo")])
+        .unwrap();
+    Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::Swap {
+                input_coin: Coin::new(1000, "uosmo"),
+                output_denom: "uion".to_string(),
+                swap_type: SwapType::MinOutputAmount(1u128.into()),
+                deadline: None,
+                execution_mode: None,
+            },
+            &[Coin::new(1000, "uosmo")],
+            &sender,
+        )
+        .map(|_| ())
+}
+
+fn denom_not_allowed(denom: &str) -> RunnerError {
+    RunnerError::ExecuteError {
+        msg: format!(
+            r#"failed to execute message; message index: 0: Denom Not Allowed: "{}": execute wasm contract failed"#,
+            denom
+        ),
+    }
+}
+
+#[test]
+fn blocklisted_intermediate_denom_rejects_route() {
+    let env = TestEnv::new();
+    update_denom_list(&env, DenomList::Blocklist, &["stake"], &[]);
+
+    let err = Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::SetRoute {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+                pool_route: vec![
+                    RouteSegment::Hop(SwapAmountInRoute {
+                        pool_id: 2,
+                        token_out_denom: "stake".to_string(),
+                    }),
+                    RouteSegment::Hop(SwapAmountInRoute {
+                        pool_id: 3,
+                        token_out_denom: "uion".to_string(),
+                    }),
+                ],
+                bidirectional: false,
+                expires_at: None,
+                propagate: false,
+            },
+            &[],
+            &env.owner,
+        )
+        .unwrap_err();
+    assert_eq!(err, denom_not_allowed("stake"));
+}
+
+#[test]
+fn blocklisted_denom_rejects_swap_on_stored_route() {
+    let env = TestEnv::new();
+    env.setup_default_route();
+    swap(&env).unwrap();
+
+    update_denom_list(&env, DenomList::Blocklist, &["stake"], &[]);
+    assert_eq!(swap(&env).unwrap_err(), denom_not_allowed("stake"));
+
+    update_denom_list(&env, DenomList::Blocklist, &[], &["stake"]);
+    swap(&env).unwrap();
+}
+
+#[test]
+fn allowlist_only_permits_listed_denoms() {
+    let env = TestEnv::new();
+    env.setup_default_route();
+    update_denom_list(&env, DenomList::Allowlist, &["uosmo"], &[]);
+    enable_allowlist(&env);
+
+    assert_eq!(swap(&env).unwrap_err(), denom_not_allowed("uion"));
+
+    update_denom_list(&env, DenomList::Allowlist, &["uion"], &[]);
+    assert_eq!(swap(&env).unwrap_err(), denom_not_allowed("stake"));
+
+    update_denom_list(&env, DenomList::Allowlist, &["stake"], &[]);
+    swap(&env).unwrap();
+
+    let res: DenomListResponse = Wasm::new(&env.app)
+        .query(
+            &env.contract_address,
+            &QueryMsg::DenomList {
+                list: DenomList::Allowlist,
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(res.denoms, vec!["stake", "uion", "uosmo"]);
+}
+
+#[test]
+fn update_denom_list_by_non_owner() {
+    let env = TestEnv::new();
+    let non_owner = env
+        .app
+        .init_account(&[Coin::new(1_000_000_000_000, "uosmo")])
+        .unwrap();
+
+    let err = Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::UpdateDenomList {
+                list: DenomList::Blocklist,
+                add: vec!["stake".to_string()],
+                remove: vec![],
+            },
+            &[],
+            &non_owner,
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        RunnerError::ExecuteError {
+            msg: "failed to execute message; message index: 0: Unauthorized: execute wasm contract failed".to_string()
+        }
+    );
+}
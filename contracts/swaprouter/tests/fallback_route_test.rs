@@ -0,0 +1,253 @@
+mod test_env;
+
+use cosmwasm_std::Coin;
+use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountInRoute;
+use osmosis_test_tube::{Gamm, Module, RunnerError, SigningAccount, Wasm};
+use swaprouter::msg::{
+    DenomList, ExecuteMsg, GetFallbackRoutesResponse, QueryMsg, SwapHistoryResponse, SwapType,
+};
+use swaprouter::state::RouteSegment;
+use test_env::*;
+
+// setup_shallow_primary_route creates pool(4): uosmo/uion with too little liquidity
+// for any meaningful swap and sets it as the uosmo -> uion route.
+fn setup_shallow_primary_route(env: &TestEnv) {
+    let gamm = Gamm::new(&env.app);
+    gamm.create_basic_pool(
+        &[Coin::new(1_000, "uion"), Coin::new(1_000, "uosmo")],
+        &env.owner,
+    )
+    .unwrap();
+
+    Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::SetRoute {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+                pool_route: vec![RouteSegment::Hop(SwapAmountInRoute {
+                    pool_id: 4,
+                    token_out_denom: "uion".to_string(),
+                })],
+                bidirectional: false,
+                expires_at: None,
+                propagate: false,
+            },
+            &[],
+            &env.owner,
+        )
+        .unwrap();
+}
+
+fn set_fallback_routes(
+    env: &TestEnv,
+    fallback_routes: Vec<Vec<SwapAmountInRoute>>,
+    signer: &SigningAccount,
+) -> Result<(), RunnerError> {
+    Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::SetFallbackRoutes {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+                fallback_routes,
+            },
+            &[],
+            signer,
+        )
+        .map(|_| ())
+}
+
+fn swap_with_min_output(
+    env: &TestEnv,
+    sender: &SigningAccount,
+    min_output: u128,
+) -> Result<(), RunnerError> {
+    Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::Swap {
+                input_coin: Coin::new(1000, "uosmo"),
+                output_denom: "uion".to_string(),
+                swap_type: SwapType::MinOutputAmount(min_output.into()),
+                deadline: None,
+                execution_mode: None,
+            },
+            &[Coin::new(1000, "uosmo")],
+            sender,
+        )
+        .map(|_| ())
+}
+
+#[test]
+fn swap_falls_back_when_primary_route_fails() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    let sender = env
+        .app
+        .init_account(&[Coin::new(1_000_000_000_000, "uosmo")])
+        .unwrap();
+
+    setup_shallow_primary_route(&env);
+    let fallback_routes = vec![
+        vec![SwapAmountInRoute {
+            pool_id: 1,
+            token_out_denom: "uion".to_string(),
+        }],
+        vec![
+            SwapAmountInRoute {
+                pool_id: 2,
+                token_out_denom: "stake".to_string(),
+            },
+            SwapAmountInRoute {
+                pool_id: 3,
+                token_out_denom: "uion".to_string(),
+            },
+        ],
+    ];
+    set_fallback_routes(&env, fallback_routes.clone(), &env.owner).unwrap();
+
+    let res: GetFallbackRoutesResponse = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::GetFallbackRoutes {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+            },
+        )
+        .unwrap();
+    let stored_routes: Vec<Vec<SwapAmountInRoute>> = res
+        .fallback_routes
+        .into_iter()
+        .map(|route| route.into_iter().map(SwapAmountInRoute::from).collect())
+        .collect();
+    assert_eq!(stored_routes, fallback_routes);
+
+    // pool(4) can not return 900uion for 1000uosmo, the first fallback route can.
+    swap_with_min_output(&env, &sender, 900).unwrap();
+
+    let res: SwapHistoryResponse = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::SwapHistory {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(res.records.len(), 1);
+    assert_eq!(res.records[0].record.pool_ids, vec![1]);
+}
+
+#[test]
+fn swap_fails_after_all_routes_fail() {
+    let env = TestEnv::new();
+    let sender = env
+        .app
+        .init_account(&[Coin::new(1_000_000_000_000, "uosmo")])
+        .unwrap();
+
+    setup_shallow_primary_route(&env);
+    set_fallback_routes(
+        &env,
+        vec![vec![SwapAmountInRoute {
+            pool_id: 1,
+            token_out_denom: "uion".to_string(),
+        }]],
+        &env.owner,
+    )
+    .unwrap();
+
+    let err = swap_with_min_output(&env, &sender, 1000000000000000000).unwrap_err();
+    match err {
+        RunnerError::ExecuteError { msg } => {
+            assert!(msg.contains("pool route [4]"), "{}", msg);
+            assert!(msg.contains("pool route [1]"), "{}", msg);
+        }
+        _ => panic!("unexpected error: {:?}", err),
+    }
+}
+
+#[test]
+fn fallback_route_through_blocklisted_denom_is_skipped() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    let sender = env
+        .app
+        .init_account(&[Coin::new(1_000_000_000_000, "uosmo")])
+        .unwrap();
+
+    setup_shallow_primary_route(&env);
+    set_fallback_routes(
+        &env,
+        vec![
+            vec![
+                SwapAmountInRoute {
+                    pool_id: 2,
+                    token_out_denom: "stake".to_string(),
+                },
+                SwapAmountInRoute {
+                    pool_id: 3,
+                    token_out_denom: "uion".to_string(),
+                },
+            ],
+            vec![SwapAmountInRoute {
+                pool_id: 1,
+                token_out_denom: "uion".to_string(),
+            }],
+        ],
+        &env.owner,
+    )
+    .unwrap();
+    wasm.execute(
+        &env.contract_address,
+        &ExecuteMsg::UpdateDenomList {
+            list: DenomList::Blocklist,
+            add: vec!["stake".to_string()],
+            remove: vec![],
+        },
+        &[],
+        &env.owner,
+    )
+    .unwrap();
+
+    swap_with_min_output(&env, &sender, 900).unwrap();
+
+    let res: SwapHistoryResponse = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::SwapHistory {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(res.records[0].record.pool_ids, vec![1]);
+}
+
+#[test]
+fn set_fallback_routes_by_non_owner() {
+    let env = TestEnv::new();
+    let non_owner = env
+        .app
+        .init_account(&[Coin::new(1_000_000_000_000, "uosmo")])
+        .unwrap();
+
+    let err = set_fallback_routes(
+        &env,
+        vec![vec![SwapAmountInRoute {
+            pool_id: 1,
+            token_out_denom: "uion".to_string(),
+        }]],
+        &non_owner,
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        err,
+        RunnerError::ExecuteError {
+            msg: "failed to execute message; message index: 0: Unauthorized: execute wasm contract failed"
+                .to_string()
+        }
+    );
+}
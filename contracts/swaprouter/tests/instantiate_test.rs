@@ -0,0 +1,94 @@
+mod test_env;
+use std::str::FromStr;
+
+use cosmwasm_std::Decimal;
+use osmosis_test_tube::{Account, Module, RunnerError, Wasm};
+use swaprouter::msg::{
+    ConfigUpdate, ExportRoutesResponse, GetConfigResponse, GetPairConfigResponse, InstantiateMsg,
+    PairConfigEntry, QueryMsg,
+};
+use swaprouter::state::{PairConfig, TwapWindows};
+use test_env::*;
+
+#[test]
+fn instantiate_with_routes_and_config() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+
+    let routes = vec![
+        route_entry("uion", "uosmo", &[(1, "uosmo")]),
+        route_entry("uosmo", "uion", &[(2, "stake"), (3, "uion")]),
+    ];
+    let pair_config = PairConfig {
+        twap_windows: Some(TwapWindows {
+            short_window_seconds: 10,
+            long_window_seconds: 60,
+            max_divergence_percentage: Decimal::from_str("1").unwrap(),
+        }),
+        ..Default::default()
+    };
+    let contract_address = env
+        .instantiate_router(&InstantiateMsg {
+            owner: env.owner.address(),
+            config: ConfigUpdate {
+                router_twap_max_window: Some(3600),
+                max_route_hops: Some(2),
+                ..Default::default()
+            },
+            routes: routes.clone(),
+            pair_configs: vec![PairConfigEntry {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+                pair_config: pair_config.clone(),
+            }],
+        })
+        .unwrap();
+
+    let res: ExportRoutesResponse = wasm
+        .query(&contract_address, &QueryMsg::ExportRoutes {})
+        .unwrap();
+    assert_eq!(res.routes, routes);
+
+    let res: GetConfigResponse = wasm
+        .query(&contract_address, &QueryMsg::GetConfig {})
+        .unwrap();
+    assert_eq!(res.config.router_twap_max_window, 3600);
+    assert_eq!(res.config.max_route_hops, 2);
+
+    let res: GetPairConfigResponse = wasm
+        .query(
+            &contract_address,
+            &QueryMsg::GetPairConfig {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.pair_config, pair_config);
+}
+
+#[test]
+fn instantiate_validates_routes_against_given_config() {
+    let env = TestEnv::new();
+
+    let err = env
+        .instantiate_router(&InstantiateMsg {
+            owner: env.owner.address(),
+            config: ConfigUpdate {
+                max_route_hops: Some(1),
+                ..Default::default()
+            },
+            routes: vec![route_entry("uosmo", "uion", &[(2, "stake"), (3, "uion")])],
+            pair_configs: vec![],
+        })
+        .unwrap_err();
+
+    match err {
+        RunnerError::ExecuteError { msg } => assert!(
+            msg.contains(r#"Invalid Pool Route: "route has 2 hops, more than the maximum of 1""#),
+            "{}",
+            msg
+        ),
+        _ => panic!("unexpected error: {:?}", err),
+    }
+}
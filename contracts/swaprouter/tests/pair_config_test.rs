@@ -0,0 +1,307 @@
+mod test_env;
+use std::str::FromStr;
+
+use cosmwasm_std::{Coin, Decimal};
+use osmosis_test_tube::{Module, RunnerError, Wasm};
+use swaprouter::msg::{ExecuteMsg, GetPairConfigResponse, QueryMsg, SwapType};
+use swaprouter::state::{PairConfig, TwapWindows};
+use test_env::*;
+
+#[test]
+fn twap_based_swap_with_short_and_long_windows() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    let sender = env
+        .app
+        .init_account(&[Coin::new(1_000_000_000_000, "uosmo")])
+        .unwrap();
+
+    // the long window must not start before the pools were created.
+    env.app.increase_time(120);
+    env.setup_default_route();
+
+    let pair_config = PairConfig {
+        twap_windows: Some(TwapWindows {
+            short_window_seconds: 10,
+            long_window_seconds: 60,
+            max_divergence_percentage: Decimal::from_str("1").unwrap(),
+        }),
+        ..Default::default()
+    };
+    wasm.execute(
+        &env.contract_address,
+        &ExecuteMsg::SetPairConfig {
+            input_denom: "uosmo".to_string(),
+            output_denom: "uion".to_string(),
+            pair_config: pair_config.clone(),
+        },
+        &[],
+        &env.owner,
+    )
+    .unwrap();
+
+    let res: GetPairConfigResponse = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::GetPairConfig {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.pair_config, pair_config);
+
+    // untouched pools have the same twap over both windows.
+    wasm.execute(
+        &env.contract_address,
+        &ExecuteMsg::Swap {
+            input_coin: Coin::new(1000, "uosmo"),
+            output_denom: "uion".to_string(),
+            swap_type: SwapType::MaxSlippagePercentage(Decimal::from_str("5").unwrap()),
+            deadline: None,
+            execution_mode: None,
+        },
+        &[Coin::new(1000, "uosmo")],
+        &sender,
+    )
+    .unwrap();
+}
+
+#[test]
+fn set_pair_config_with_short_window_not_shorter_than_long_window() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+
+    let err = wasm
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::SetPairConfig {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+                pair_config: PairConfig {
+                    twap_windows: Some(TwapWindows {
+                        short_window_seconds: 60,
+                        long_window_seconds: 60,
+                        max_divergence_percentage: Decimal::from_str("1").unwrap(),
+                    }),
+                    ..Default::default()
+                },
+            },
+            &[],
+            &env.owner,
+        )
+        .unwrap_err();
+
+    assert_eq!(
+        err,
+        RunnerError::ExecuteError {
+            msg: r#"failed to execute message; message index: 0: Invalid Pair Config: "short twap window must be positive and shorter than long twap window": execute wasm contract failed"#.to_string()
+        }
+    );
+}
+
+fn set_pair_limits(env: &TestEnv, pair_config: PairConfig) {
+    Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::SetPairConfig {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+                pair_config,
+            },
+            &[],
+            &env.owner,
+        )
+        .unwrap();
+}
+
+fn swap_uosmo(env: &TestEnv, amount: u128, swap_type: SwapType) -> Result<(), RunnerError> {
+    let sender = env
+        .app
+        .init_account(&[Coin::new(1_000_000_000_000, "uosmo")])
+        .unwrap();
+    Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::Swap {
+                input_coin: Coin::new(amount, "uosmo"),
+                output_denom: "uion".to_string(),
+                swap_type,
+                deadline: None,
+                execution_mode: None,
+            },
+            &[Coin::new(amount, "uosmo")],
+            &sender,
+        )
+        .map(|_| ())
+}
+
+#[test]
+fn swap_with_slippage_above_pair_maximum() {
+    let env = TestEnv::new();
+    env.setup_default_route();
+    set_pair_limits(
+        &env,
+        PairConfig {
+            max_slippage_percentage: Some(Decimal::from_str("5").unwrap()),
+            ..Default::default()
+        },
+    );
+
+    let err = swap_uosmo(
+        &env,
+        1000,
+        SwapType::MaxSlippagePercentage(Decimal::from_str("99").unwrap()),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        RunnerError::ExecuteError {
+            msg: r#"failed to execute message; message index: 0: Pair Limit Exceeded: "slippage of 99% is more than the maximum of 5%": execute wasm contract failed"#.to_string()
+        }
+    );
+}
+
+#[test]
+fn swap_with_min_output_below_pair_slippage_floor() {
+    let env = TestEnv::new();
+    env.setup_default_route();
+    set_pair_limits(
+        &env,
+        PairConfig {
+            max_slippage_percentage: Some(Decimal::from_str("5").unwrap()),
+            ..Default::default()
+        },
+    );
+
+    // the pools of the default route are balanced, so the floor is 95% of the input.
+    let err = swap_uosmo(&env, 1000, SwapType::MinOutputAmount(0u128.into())).unwrap_err();
+    assert_eq!(
+        err,
+        RunnerError::ExecuteError {
+            msg: r#"failed to execute message; message index: 0: Pair Limit Exceeded: "minimum output of 0uion is less than the 950uion allowed by the maximum slippage of 5%": execute wasm contract failed"#.to_string()
+        }
+    );
+    swap_uosmo(&env, 1000, SwapType::MinOutputAmount(950u128.into())).unwrap();
+}
+
+#[test]
+fn swap_with_input_below_pair_minimum() {
+    let env = TestEnv::new();
+    env.setup_default_route();
+    set_pair_limits(
+        &env,
+        PairConfig {
+            min_input_amount: Some(5000u128.into()),
+            ..Default::default()
+        },
+    );
+
+    let err = swap_uosmo(&env, 1000, SwapType::MinOutputAmount(1u128.into())).unwrap_err();
+    assert_eq!(
+        err,
+        RunnerError::ExecuteError {
+            msg: r#"failed to execute message; message index: 0: Pair Limit Exceeded: "input of 1000uosmo is less than the minimum of 5000uosmo": execute wasm contract failed"#.to_string()
+        }
+    );
+    swap_uosmo(&env, 5000, SwapType::MinOutputAmount(1u128.into())).unwrap();
+}
+
+#[test]
+fn swap_with_input_above_pair_liquidity_percentage() {
+    let env = TestEnv::new();
+    env.setup_default_route();
+    // the first pool of the default route holds 100_000_000 uosmo.
+    set_pair_limits(
+        &env,
+        PairConfig {
+            max_input_liquidity_percentage: Some(Decimal::from_str("1").unwrap()),
+            ..Default::default()
+        },
+    );
+
+    let err = swap_uosmo(&env, 2_000_000, SwapType::MinOutputAmount(1u128.into())).unwrap_err();
+    assert_eq!(
+        err,
+        RunnerError::ExecuteError {
+            msg: r#"failed to execute message; message index: 0: Pair Limit Exceeded: "input of 2000000uosmo is more than 1% of the uosmo liquidity of pool id 2": execute wasm contract failed"#.to_string()
+        }
+    );
+    swap_uosmo(&env, 1_000_000, SwapType::MinOutputAmount(1u128.into())).unwrap();
+}
+
+#[test]
+fn set_pair_config_with_slippage_above_hundred_percent() {
+    let env = TestEnv::new();
+
+    let err = Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::SetPairConfig {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+                pair_config: PairConfig {
+                    max_slippage_percentage: Some(Decimal::from_str("101").unwrap()),
+                    ..Default::default()
+                },
+            },
+            &[],
+            &env.owner,
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        RunnerError::ExecuteError {
+            msg: r#"failed to execute message; message index: 0: Invalid Pair Config: "max slippage percentage must not be more than 100": execute wasm contract failed"#.to_string()
+        }
+    );
+}
+
+#[test]
+fn twap_based_swap_rejected_when_short_window_diverges() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+
+    // the long window must not start before the pools were created.
+    env.app.increase_time(120);
+    env.setup_default_route();
+    set_pair_limits(
+        &env,
+        PairConfig {
+            twap_windows: Some(TwapWindows {
+                short_window_seconds: 10,
+                long_window_seconds: 60,
+                max_divergence_percentage: Decimal::from_str("1").unwrap(),
+            }),
+            ..Default::default()
+        },
+    );
+
+    // moving the pools leaves the long window mostly at the previous price.
+    swap_uosmo(&env, 50_000_000, SwapType::MinOutputAmount(1u128.into())).unwrap();
+    env.app.increase_time(10);
+
+    let sender = env
+        .app
+        .init_account(&[Coin::new(1_000_000_000_000, "uosmo")])
+        .unwrap();
+    let err = wasm
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::Swap {
+                input_coin: Coin::new(1000, "uosmo"),
+                output_denom: "uion".to_string(),
+                swap_type: SwapType::MaxSlippagePercentage(Decimal::from_str("5").unwrap()),
+                deadline: None,
+                execution_mode: None,
+            },
+            &[Coin::new(1000, "uosmo")],
+            &sender,
+        )
+        .unwrap_err();
+    assert!(
+        format!("{:?}", err).contains("diverges from long window TWAP"),
+        "{:?}",
+        err
+    );
+}
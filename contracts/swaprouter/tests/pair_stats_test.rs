@@ -0,0 +1,62 @@
+mod test_env;
+use cosmwasm_std::{Coin, Decimal, Uint128};
+use osmosis_test_tube::{Module, Wasm};
+use swaprouter::msg::{AllPairStatsResponse, PairStatsResponse, QueryMsg};
+use test_env::*;
+
+#[test]
+fn pair_stats_accumulate_over_swaps() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    let sender = env
+        .app
+        .init_account(&[Coin::new(1_000_000_000_000, "uosmo")])
+        .unwrap();
+
+    env.setup_default_route();
+    env.swap_default_route(&sender, 1000);
+    env.swap_default_route(&sender, 3000);
+
+    let PairStatsResponse { stats } = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::PairStats {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(stats.swap_count, 2);
+    assert_eq!(stats.total_input, Uint128::new(4000));
+    assert!(stats.total_output > Uint128::zero());
+    assert!(stats.last_execution_price > Decimal::zero());
+
+    let res: AllPairStatsResponse = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::AllPairStats {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(res.pairs.len(), 1);
+    assert_eq!(res.pairs[0].input_denom, "uosmo");
+    assert_eq!(res.pairs[0].output_denom, "uion");
+    assert_eq!(res.pairs[0].stats, stats);
+}
+
+#[test]
+fn pair_stats_for_pair_without_swaps_is_not_found() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+
+    let res = wasm.query::<QueryMsg, PairStatsResponse>(
+        &env.contract_address,
+        &QueryMsg::PairStats {
+            input_denom: "uosmo".to_string(),
+            output_denom: "uion".to_string(),
+        },
+    );
+    assert!(res.is_err());
+}
@@ -0,0 +1,191 @@
+mod test_env;
+
+use cosmwasm_std::Coin;
+use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountInRoute;
+use osmosis_test_tube::{Module, RunnerError, Wasm};
+use swaprouter::msg::{
+    DenomPair, ExecuteMsg, GetFallbackRoutesResponse, GetRouteResponse, QueryMsg,
+    RoutesByPoolResponse,
+};
+use swaprouter::state::RouteSegment;
+use test_env::*;
+
+fn set_route(env: &TestEnv, input_denom: &str, output_denom: &str, pool_route: &[(u64, &str)]) {
+    Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::SetRoute {
+                input_denom: input_denom.to_string(),
+                output_denom: output_denom.to_string(),
+                pool_route: pool_route
+                    .iter()
+                    .map(|(pool_id, token_out_denom)| {
+                        RouteSegment::Hop(SwapAmountInRoute {
+                            pool_id: *pool_id,
+                            token_out_denom: token_out_denom.to_string(),
+                        })
+                    })
+                    .collect(),
+                bidirectional: false,
+                expires_at: None,
+                propagate: false,
+            },
+            &[],
+            &env.owner,
+        )
+        .unwrap();
+}
+
+fn routes_by_pool(env: &TestEnv, pool_id: u64) -> Vec<DenomPair> {
+    Wasm::new(&env.app)
+        .query::<QueryMsg, RoutesByPoolResponse>(
+            &env.contract_address,
+            &QueryMsg::RoutesByPool { pool_id },
+        )
+        .unwrap()
+        .routes
+}
+
+fn pair(input_denom: &str, output_denom: &str) -> DenomPair {
+    DenomPair {
+        input_denom: input_denom.to_string(),
+        output_denom: output_denom.to_string(),
+    }
+}
+
+#[test]
+fn routes_by_pool_follows_route_changes() {
+    let env = TestEnv::new();
+    env.setup_default_route();
+    set_route(&env, "stake", "uion", &[(3, "uion")]);
+
+    assert_eq!(routes_by_pool(&env, 1), vec![]);
+    assert_eq!(routes_by_pool(&env, 2), vec![pair("uosmo", "uion")]);
+    assert_eq!(
+        routes_by_pool(&env, 3),
+        vec![pair("stake", "uion"), pair("uosmo", "uion")]
+    );
+
+    // overriding the route drops the pools it no longer uses.
+    set_route(&env, "uosmo", "uion", &[(1, "uion")]);
+
+    assert_eq!(routes_by_pool(&env, 1), vec![pair("uosmo", "uion")]);
+    assert_eq!(routes_by_pool(&env, 2), vec![]);
+    assert_eq!(routes_by_pool(&env, 3), vec![pair("stake", "uion")]);
+}
+
+#[test]
+fn disable_pool_removes_every_route_using_it() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    env.setup_default_route();
+    set_route(&env, "stake", "uion", &[(3, "uion")]);
+    set_route(&env, "uosmo", "stake", &[(2, "stake")]);
+
+    wasm.execute(
+        &env.contract_address,
+        &ExecuteMsg::DisablePool { pool_id: 3 },
+        &[],
+        &env.owner,
+    )
+    .unwrap();
+
+    let get_route = |input_denom: &str, output_denom: &str| {
+        wasm.query::<QueryMsg, GetRouteResponse>(
+            &env.contract_address,
+            &QueryMsg::GetRoute {
+                input_denom: input_denom.to_string(),
+                output_denom: output_denom.to_string(),
+            },
+        )
+    };
+    assert!(get_route("uosmo", "uion").is_err());
+    assert!(get_route("stake", "uion").is_err());
+    assert!(get_route("uosmo", "stake").is_ok());
+
+    assert_eq!(routes_by_pool(&env, 3), vec![]);
+    assert_eq!(routes_by_pool(&env, 2), vec![pair("uosmo", "stake")]);
+}
+
+#[test]
+fn disable_pool_removes_fallback_routes_using_it() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    env.setup_default_route();
+
+    let pool_1_route = vec![SwapAmountInRoute {
+        pool_id: 1,
+        token_out_denom: "uion".to_string(),
+    }];
+    let pool_3_route = vec![
+        SwapAmountInRoute {
+            pool_id: 2,
+            token_out_denom: "stake".to_string(),
+        },
+        SwapAmountInRoute {
+            pool_id: 3,
+            token_out_denom: "uion".to_string(),
+        },
+    ];
+    wasm.execute(
+        &env.contract_address,
+        &ExecuteMsg::SetFallbackRoutes {
+            input_denom: "uosmo".to_string(),
+            output_denom: "uion".to_string(),
+            fallback_routes: vec![pool_3_route, pool_1_route.clone()],
+        },
+        &[],
+        &env.owner,
+    )
+    .unwrap();
+
+    wasm.execute(
+        &env.contract_address,
+        &ExecuteMsg::DisablePool { pool_id: 3 },
+        &[],
+        &env.owner,
+    )
+    .unwrap();
+
+    let res: GetFallbackRoutesResponse = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::GetFallbackRoutes {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+            },
+        )
+        .unwrap();
+    let fallback_routes: Vec<Vec<SwapAmountInRoute>> = res
+        .fallback_routes
+        .into_iter()
+        .map(|route| route.into_iter().map(SwapAmountInRoute::from).collect())
+        .collect();
+    assert_eq!(fallback_routes, vec![pool_1_route]);
+}
+
+#[test]
+fn disable_pool_by_non_owner() {
+    let env = TestEnv::new();
+    let non_owner = env
+        .app
+        .init_account(&[Coin::new(1_000_000_000_000, "uosmo")])
+        .unwrap();
+
+    let err = Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::DisablePool { pool_id: 1 },
+            &[],
+            &non_owner,
+        )
+        .unwrap_err();
+
+    assert_eq!(
+        err,
+        RunnerError::ExecuteError {
+            msg: "failed to execute message; message index: 0: Unauthorized: execute wasm contract failed"
+                .to_string()
+        }
+    );
+}
@@ -0,0 +1,87 @@
+mod test_env;
+use std::str::FromStr;
+
+use cosmwasm_std::{Addr, Coin, Decimal};
+use osmosis_test_tube::{Module, RunnerError, Wasm};
+use swaprouter::msg::{ExecuteMsg, SwapType};
+use swaprouter::state::{PairConfig, PriceSource};
+use test_env::*;
+
+#[test]
+fn swap_priced_by_geometric_twap() {
+    let (_env, res) = set_price_source_and_swap(|_| PriceSource::GeometricTwap);
+    assert!(res.is_ok(), "{:?}", res.unwrap_err());
+}
+
+#[test]
+fn swap_priced_by_oracle() {
+    // the 1:1 pools return ~1000 uion, well above the 500 uion minimum.
+    let (_env, res) = set_price_source_and_swap(|env| PriceSource::Oracle {
+        contract_address: Addr::unchecked(
+            env.instantiate_mock_oracle(Decimal::from_str("0.5").unwrap()),
+        ),
+    });
+    assert!(res.is_ok(), "{:?}", res.unwrap_err());
+}
+
+#[test]
+fn swap_priced_by_oracle_above_pool_price() {
+    // the oracle asks for 10 uion per uosmo while the pools only offer ~1.
+    let (_env, res) = set_price_source_and_swap(|env| PriceSource::Oracle {
+        contract_address: Addr::unchecked(
+            env.instantiate_mock_oracle(Decimal::from_str("10").unwrap()),
+        ),
+    });
+    assert_eq!(
+        res.unwrap_err(),
+        RunnerError::ExecuteError {
+            msg: "failed to execute message; message index: 0: dispatch: submessages: uion token is lesser than min amount: calculated amount is lesser than min amount".to_string()
+        }
+    );
+}
+
+// ======= helpers ========
+
+fn set_price_source_and_swap(
+    price_source: impl FnOnce(&TestEnv) -> PriceSource,
+) -> (TestEnv, Result<(), RunnerError>) {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    let sender = env
+        .app
+        .init_account(&[Coin::new(1_000_000_000_000, "uosmo")])
+        .unwrap();
+
+    env.setup_default_route();
+    wasm.execute(
+        &env.contract_address,
+        &ExecuteMsg::SetPairConfig {
+            input_denom: "uosmo".to_string(),
+            output_denom: "uion".to_string(),
+            pair_config: PairConfig {
+                price_source: price_source(&env),
+                ..Default::default()
+            },
+        },
+        &[],
+        &env.owner,
+    )
+    .unwrap();
+
+    let res = wasm
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::Swap {
+                input_coin: Coin::new(1000, "uosmo"),
+                output_denom: "uion".to_string(),
+                swap_type: SwapType::MaxSlippagePercentage(Decimal::from_str("5").unwrap()),
+                deadline: None,
+                execution_mode: None,
+            },
+            &[Coin::new(1000, "uosmo")],
+            &sender,
+        )
+        .map(|_| ());
+
+    (env, res)
+}
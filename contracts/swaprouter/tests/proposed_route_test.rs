@@ -0,0 +1,426 @@
+mod test_env;
+
+use cosmwasm_std::{Addr, Decimal};
+use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountInRoute;
+use osmosis_test_tube::{Account, Module, RunnerError, Wasm};
+use swaprouter::msg::{
+    ConfigUpdate, ExecuteMsg, GetFallbackRoutesResponse, GetPairConfigResponse, GetRouteResponse,
+    ProposedRoutesResponse, QueryMsg, RoutesByPoolResponse,
+};
+use swaprouter::state::{DenomAlias, PairConfig, PriceSource, RouteSegment};
+use test_env::*;
+
+const TIMELOCK_SECONDS: u64 = 3600;
+
+fn set_route_timelock(env: &TestEnv, route_timelock_seconds: u64) {
+    Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::UpdateConfig(ConfigUpdate {
+                route_timelock_seconds: Some(route_timelock_seconds),
+                ..Default::default()
+            }),
+            &[],
+            &env.owner,
+        )
+        .unwrap();
+}
+
+fn propose_msg() -> ExecuteMsg {
+    ExecuteMsg::ProposeRoute {
+        input_denom: "uosmo".to_string(),
+        output_denom: "uion".to_string(),
+        pool_route: vec![SwapAmountInRoute {
+            pool_id: 1,
+            token_out_denom: "uion".to_string(),
+        }],
+        bidirectional: false,
+        expires_at: None,
+        propagate: false,
+    }
+}
+
+fn execute_proposal_msg() -> ExecuteMsg {
+    ExecuteMsg::ExecuteProposedRoute {
+        input_denom: "uosmo".to_string(),
+        output_denom: "uion".to_string(),
+    }
+}
+
+fn proposed_routes(env: &TestEnv) -> ProposedRoutesResponse {
+    Wasm::new(&env.app)
+        .query(
+            &env.contract_address,
+            &QueryMsg::ProposedRoutes {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap()
+}
+
+#[test]
+fn set_route_is_rejected_under_timelock() {
+    let env = TestEnv::new();
+    set_route_timelock(&env, TIMELOCK_SECONDS);
+
+    let wasm = Wasm::new(&env.app);
+    let err = wasm
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::SetRoute {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+                pool_route: vec![RouteSegment::Hop(SwapAmountInRoute {
+                    pool_id: 1,
+                    token_out_denom: "uion".to_string(),
+                })],
+                bidirectional: false,
+                expires_at: None,
+                propagate: false,
+            },
+            &[],
+            &env.owner,
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        RunnerError::ExecuteError {
+            msg: r#"failed to execute message; message index: 0: Route Timelocked: "routes must be set with ProposeRoute": execute wasm contract failed"#.to_string()
+        }
+    );
+}
+
+#[test]
+fn proposed_route_executes_after_timelock() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    set_route_timelock(&env, TIMELOCK_SECONDS);
+
+    wasm.execute(&env.contract_address, &propose_msg(), &[], &env.owner)
+        .unwrap();
+
+    let res = proposed_routes(&env);
+    assert_eq!(res.proposals.len(), 1);
+    assert_eq!(res.proposals[0].input_denom, "uosmo");
+    assert_eq!(res.proposals[0].output_denom, "uion");
+    assert_eq!(
+        res.proposals[0].proposal.proposed_by.as_str(),
+        env.owner.address()
+    );
+
+    let err = wasm
+        .execute(
+            &env.contract_address,
+            &execute_proposal_msg(),
+            &[],
+            &env.owner,
+        )
+        .unwrap_err();
+    assert!(
+        format!("{:?}", err).contains("proposed route is executable after"),
+        "{:?}",
+        err
+    );
+
+    env.app.increase_time(TIMELOCK_SECONDS);
+    wasm.execute(
+        &env.contract_address,
+        &execute_proposal_msg(),
+        &[],
+        &env.owner,
+    )
+    .unwrap();
+
+    let res: GetRouteResponse = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::GetRoute {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.pool_route.len(), 1);
+    assert_eq!(res.pool_route[0].pool_id, 1);
+    assert!(proposed_routes(&env).proposals.is_empty());
+}
+
+#[test]
+fn raised_timelock_delays_pending_proposals() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    set_route_timelock(&env, TIMELOCK_SECONDS);
+
+    wasm.execute(&env.contract_address, &propose_msg(), &[], &env.owner)
+        .unwrap();
+    let proposed_at = proposed_routes(&env).proposals[0].proposal.proposed_at;
+    set_route_timelock(&env, 2 * TIMELOCK_SECONDS);
+    assert_eq!(
+        proposed_routes(&env).proposals[0].executable_after,
+        proposed_at.plus_seconds(2 * TIMELOCK_SECONDS)
+    );
+
+    env.app.increase_time(TIMELOCK_SECONDS);
+    let err = wasm
+        .execute(
+            &env.contract_address,
+            &execute_proposal_msg(),
+            &[],
+            &env.owner,
+        )
+        .unwrap_err();
+    assert!(
+        format!("{:?}", err).contains("proposed route is executable after"),
+        "{:?}",
+        err
+    );
+
+    env.app.increase_time(TIMELOCK_SECONDS);
+    wasm.execute(
+        &env.contract_address,
+        &execute_proposal_msg(),
+        &[],
+        &env.owner,
+    )
+    .unwrap();
+}
+
+#[test]
+fn cancelled_proposal_can_not_be_executed() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    set_route_timelock(&env, TIMELOCK_SECONDS);
+
+    wasm.execute(&env.contract_address, &propose_msg(), &[], &env.owner)
+        .unwrap();
+    wasm.execute(
+        &env.contract_address,
+        &ExecuteMsg::CancelProposedRoute {
+            input_denom: "uosmo".to_string(),
+            output_denom: "uion".to_string(),
+        },
+        &[],
+        &env.owner,
+    )
+    .unwrap();
+    assert!(proposed_routes(&env).proposals.is_empty());
+
+    env.app.increase_time(TIMELOCK_SECONDS);
+    let res = wasm.execute(
+        &env.contract_address,
+        &execute_proposal_msg(),
+        &[],
+        &env.owner,
+    );
+    assert!(res.is_err());
+}
+
+#[test]
+fn route_timelock_can_not_be_lowered() {
+    let env = TestEnv::new();
+    set_route_timelock(&env, TIMELOCK_SECONDS);
+
+    let err = Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::UpdateConfig(ConfigUpdate {
+                route_timelock_seconds: Some(0),
+                ..Default::default()
+            }),
+            &[],
+            &env.owner,
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        RunnerError::ExecuteError {
+            msg: r#"failed to execute message; message index: 0: Route Timelocked: "route timelock can not be lowered": execute wasm contract failed"#.to_string()
+        }
+    );
+}
+
+#[test]
+fn disable_pool_is_instant_under_timelock() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    env.setup_default_route();
+    set_route_timelock(&env, TIMELOCK_SECONDS);
+
+    wasm.execute(
+        &env.contract_address,
+        &ExecuteMsg::DisablePool { pool_id: 2 },
+        &[],
+        &env.owner,
+    )
+    .unwrap();
+
+    let res: RoutesByPoolResponse = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::RoutesByPool { pool_id: 2 },
+        )
+        .unwrap();
+    assert!(res.routes.is_empty());
+}
+
+#[test]
+fn disable_pool_cancels_proposals_using_it() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    set_route_timelock(&env, TIMELOCK_SECONDS);
+    wasm.execute(&env.contract_address, &propose_msg(), &[], &env.owner)
+        .unwrap();
+
+    let res = wasm
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::DisablePool { pool_id: 1 },
+            &[],
+            &env.owner,
+        )
+        .unwrap();
+
+    assert!(res
+        .events
+        .iter()
+        .any(|event| event.ty == "wasm-proposed_route_cancelled"));
+    assert!(proposed_routes(&env).proposals.is_empty());
+}
+
+#[test]
+fn routing_settings_are_rejected_under_timelock() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    set_route_timelock(&env, TIMELOCK_SECONDS);
+
+    let cases = [
+        (
+            ExecuteMsg::SetFallbackRoutes {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+                fallback_routes: vec![vec![SwapAmountInRoute {
+                    pool_id: 1,
+                    token_out_denom: "uion".to_string(),
+                }]],
+            },
+            "fallback routes can only be removed while the route timelock is configured",
+        ),
+        (
+            ExecuteMsg::SetPairConfig {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+                pair_config: PairConfig {
+                    price_source: PriceSource::Oracle {
+                        contract_address: Addr::unchecked(env.owner.address()),
+                    },
+                    ..Default::default()
+                },
+            },
+            "pair configs can only be tightened while the route timelock is configured",
+        ),
+        (
+            ExecuteMsg::SetDenomAlias {
+                symbol: "OSMO".to_string(),
+                alias: DenomAlias {
+                    denom: "uosmo".to_string(),
+                    exponent: 6,
+                    display: "osmo".to_string(),
+                },
+            },
+            "denom aliases can not be given another denom while the route timelock is configured",
+        ),
+    ];
+    for (msg, reason) in cases {
+        let err = wasm
+            .execute(&env.contract_address, &msg, &[], &env.owner)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            RunnerError::ExecuteError {
+                msg: format!(
+                    r#"failed to execute message; message index: 0: Route Timelocked: "{reason}": execute wasm contract failed"#
+                )
+            }
+        );
+    }
+}
+
+#[test]
+fn restrictive_routing_settings_apply_under_timelock() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    let execute = |msg: &ExecuteMsg| wasm.execute(&env.contract_address, msg, &[], &env.owner);
+    let set_fallback_routes = |pool_ids: &[u64]| ExecuteMsg::SetFallbackRoutes {
+        input_denom: "uosmo".to_string(),
+        output_denom: "uion".to_string(),
+        fallback_routes: pool_ids
+            .iter()
+            .map(|&pool_id| {
+                vec![SwapAmountInRoute {
+                    pool_id,
+                    token_out_denom: "uion".to_string(),
+                }]
+            })
+            .collect(),
+    };
+    let set_pair_config =
+        |price_source: PriceSource, max_slippage_percentage: u64| ExecuteMsg::SetPairConfig {
+            input_denom: "uosmo".to_string(),
+            output_denom: "uion".to_string(),
+            pair_config: PairConfig {
+                price_source,
+                max_slippage_percentage: Some(Decimal::percent(max_slippage_percentage)),
+                ..Default::default()
+            },
+        };
+    let set_denom_alias = |display: &str| ExecuteMsg::SetDenomAlias {
+        symbol: "OSMO".to_string(),
+        alias: DenomAlias {
+            denom: "uosmo".to_string(),
+            exponent: 6,
+            display: display.to_string(),
+        },
+    };
+    let oracle = PriceSource::Oracle {
+        contract_address: Addr::unchecked(env.owner.address()),
+    };
+
+    execute(&set_fallback_routes(&[1, 1])).unwrap();
+    execute(&set_pair_config(oracle, 10)).unwrap();
+    execute(&set_denom_alias("OSMO")).unwrap();
+    set_route_timelock(&env, TIMELOCK_SECONDS);
+
+    execute(&set_fallback_routes(&[1])).unwrap();
+    execute(&set_fallback_routes(&[])).unwrap();
+    let res: GetFallbackRoutesResponse = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::GetFallbackRoutes {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+            },
+        )
+        .unwrap();
+    assert!(res.fallback_routes.is_empty());
+
+    // the compromised oracle is replaced by the TWAP module and the slippage cap tightened.
+    execute(&set_pair_config(PriceSource::ArithmeticTwap, 5)).unwrap();
+    assert!(execute(&set_pair_config(PriceSource::ArithmeticTwap, 20)).is_err());
+    let res: GetPairConfigResponse = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::GetPairConfig {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.pair_config.price_source, PriceSource::ArithmeticTwap);
+    assert_eq!(
+        res.pair_config.max_slippage_percentage,
+        Some(Decimal::percent(5))
+    );
+
+    execute(&set_denom_alias("osmo")).unwrap();
+}
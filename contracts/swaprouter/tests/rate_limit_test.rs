@@ -0,0 +1,214 @@
+mod test_env;
+
+use cosmwasm_std::{Coin, Uint128};
+use osmosis_test_tube::{Account, Module, RunnerError, SigningAccount, Wasm};
+use swaprouter::msg::{ExecuteMsg, ExecutionMode, QueryMsg, RemainingCapacityResponse, SwapType};
+use swaprouter::state::{RateLimit, RateLimitWindow};
+use test_env::*;
+
+const WINDOW_SECONDS: u64 = 60;
+
+fn set_rate_limit(env: &TestEnv, rate_limit: RateLimit) {
+    Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::SetRateLimit {
+                denom: "uosmo".to_string(),
+                rate_limit: Some(rate_limit),
+            },
+            &[],
+            &env.owner,
+        )
+        .unwrap();
+}
+
+fn swap(env: &TestEnv, sender: &SigningAccount, amount: u128) -> Result<(), RunnerError> {
+    Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::Swap {
+                input_coin: Coin::new(amount, "uosmo"),
+                output_denom: "uion".to_string(),
+                swap_type: SwapType::MinOutputAmount(1u128.into()),
+                deadline: None,
+                execution_mode: None,
+            },
+            &[Coin::new(amount, "uosmo")],
+            sender,
+        )
+        .map(|_| ())
+}
+
+fn remaining_capacity(env: &TestEnv, sender: Option<&SigningAccount>) -> RemainingCapacityResponse {
+    Wasm::new(&env.app)
+        .query(
+            &env.contract_address,
+            &QueryMsg::RemainingCapacity {
+                denom: "uosmo".to_string(),
+                sender: sender.map(|sender| sender.address()),
+            },
+        )
+        .unwrap()
+}
+
+fn init_sender(env: &TestEnv) -> SigningAccount {
+    env.app
+        .init_account(&[Coin::new(1_000_000_000, "uosmo")])
+        .unwrap()
+}
+
+#[test]
+fn rolling_window_caps_denom_volume() {
+    let env = TestEnv::new();
+    env.setup_default_route();
+    set_rate_limit(
+        &env,
+        RateLimit {
+            window: RateLimitWindow::Seconds(WINDOW_SECONDS),
+            max_volume: Uint128::new(1500),
+            max_volume_per_sender: None,
+        },
+    );
+    let sender = init_sender(&env);
+
+    swap(&env, &sender, 1000).unwrap();
+    assert_eq!(
+        remaining_capacity(&env, None).remaining,
+        Some(Uint128::new(500))
+    );
+
+    let err = swap(&env, &sender, 1000).unwrap_err();
+    assert_eq!(
+        err,
+        RunnerError::ExecuteError {
+            msg: r#"failed to execute message; message index: 0: Rate Limit Exceeded: "swapping 1000uosmo exceeds the remaining capacity of 500uosmo": execute wasm contract failed"#.to_string()
+        }
+    );
+
+    env.app.increase_time(WINDOW_SECONDS);
+    swap(&env, &sender, 1000).unwrap();
+}
+
+#[test]
+fn per_sender_cap_limits_each_sender() {
+    let env = TestEnv::new();
+    env.setup_default_route();
+    set_rate_limit(
+        &env,
+        RateLimit {
+            window: RateLimitWindow::Seconds(WINDOW_SECONDS),
+            max_volume: Uint128::new(10_000),
+            max_volume_per_sender: Some(Uint128::new(1500)),
+        },
+    );
+    let sender = init_sender(&env);
+    let other_sender = init_sender(&env);
+
+    swap(&env, &sender, 1000).unwrap();
+    let err = swap(&env, &sender, 1000).unwrap_err();
+    assert!(
+        format!("{:?}", err).contains("remaining capacity of the sender of 500uosmo"),
+        "{:?}",
+        err
+    );
+    swap(&env, &other_sender, 1000).unwrap();
+
+    let res = remaining_capacity(&env, Some(&sender));
+    assert_eq!(res.remaining, Some(Uint128::new(8000)));
+    assert_eq!(res.sender_remaining, Some(Uint128::new(500)));
+}
+
+#[test]
+fn refunded_swap_releases_capacity() {
+    let env = TestEnv::new();
+    env.setup_default_route();
+    set_rate_limit(
+        &env,
+        RateLimit {
+            window: RateLimitWindow::Seconds(WINDOW_SECONDS),
+            max_volume: Uint128::new(1500),
+            max_volume_per_sender: Some(Uint128::new(1500)),
+        },
+    );
+    let sender = init_sender(&env);
+
+    // minimum output can not be met, so the input is refunded.
+    Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::Swap {
+                input_coin: Coin::new(1000, "uosmo"),
+                output_denom: "uion".to_string(),
+                swap_type: SwapType::MinOutputAmount(1_000_000_000u128.into()),
+                deadline: None,
+                execution_mode: Some(ExecutionMode::BestEffort),
+            },
+            &[Coin::new(1000, "uosmo")],
+            &sender,
+        )
+        .unwrap();
+
+    let res = remaining_capacity(&env, Some(&sender));
+    assert_eq!(res.remaining, Some(Uint128::new(1500)));
+    assert_eq!(res.sender_remaining, Some(Uint128::new(1500)));
+}
+
+#[test]
+fn epoch_window_caps_denom_volume() {
+    let env = TestEnv::new();
+    env.setup_default_route();
+    set_rate_limit(
+        &env,
+        RateLimit {
+            window: RateLimitWindow::Epoch {
+                identifier: "day".to_string(),
+            },
+            max_volume: Uint128::new(1500),
+            max_volume_per_sender: None,
+        },
+    );
+    let sender = init_sender(&env);
+
+    swap(&env, &sender, 1000).unwrap();
+    assert!(swap(&env, &sender, 1000).is_err());
+    assert_eq!(
+        remaining_capacity(&env, None).remaining,
+        Some(Uint128::new(500))
+    );
+}
+
+#[test]
+fn unlimited_denom_has_no_capacity() {
+    let env = TestEnv::new();
+
+    let res = remaining_capacity(&env, None);
+    assert_eq!(res.rate_limit, None);
+    assert_eq!(res.remaining, None);
+}
+
+#[test]
+fn set_rate_limit_by_non_owner() {
+    let env = TestEnv::new();
+    let non_owner = env
+        .app
+        .init_account(&[Coin::new(1_000_000_000_000, "uosmo")])
+        .unwrap();
+
+    let err = Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::SetRateLimit {
+                denom: "uosmo".to_string(),
+                rate_limit: None,
+            },
+            &[],
+            &non_owner,
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        RunnerError::ExecuteError {
+            msg: "failed to execute message; message index: 0: Unauthorized: execute wasm contract failed".to_string()
+        }
+    );
+}
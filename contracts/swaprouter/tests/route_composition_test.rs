@@ -0,0 +1,342 @@
+mod test_env;
+
+use cosmwasm_std::{Coin, Timestamp};
+use osmosis_std::types::cosmwasm::wasm::v1::MsgExecuteContractResponse;
+use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountInRoute;
+use osmosis_test_tube::{Gamm, Module, RunnerError, RunnerExecuteResult, Wasm};
+use swaprouter::msg::{
+    ConfigUpdate, DenomPair, ExecuteMsg, GetRouteResponse, QueryMsg, RouteDependentsResponse,
+};
+use swaprouter::state::RouteSegment;
+use test_env::*;
+
+fn hop(pool_id: u64, token_out_denom: &str) -> RouteSegment {
+    RouteSegment::Hop(SwapAmountInRoute {
+        pool_id,
+        token_out_denom: token_out_denom.to_string(),
+    })
+}
+
+fn stored(input_denom: &str, output_denom: &str) -> RouteSegment {
+    RouteSegment::Route {
+        input_denom: input_denom.to_string(),
+        output_denom: output_denom.to_string(),
+    }
+}
+
+fn set_route(
+    env: &TestEnv,
+    input_denom: &str,
+    output_denom: &str,
+    pool_route: Vec<RouteSegment>,
+    propagate: bool,
+) -> RunnerExecuteResult<MsgExecuteContractResponse> {
+    Wasm::new(&env.app).execute(
+        &env.contract_address,
+        &ExecuteMsg::SetRoute {
+            input_denom: input_denom.to_string(),
+            output_denom: output_denom.to_string(),
+            pool_route,
+            bidirectional: false,
+            expires_at: None,
+            propagate,
+        },
+        &[],
+        &env.owner,
+    )
+}
+
+fn route_pool_ids(env: &TestEnv, input_denom: &str, output_denom: &str) -> Vec<u64> {
+    let res: GetRouteResponse = Wasm::new(&env.app)
+        .query(
+            &env.contract_address,
+            &QueryMsg::GetRoute {
+                input_denom: input_denom.to_string(),
+                output_denom: output_denom.to_string(),
+            },
+        )
+        .unwrap();
+    res.pool_route.iter().map(|hop| hop.pool_id).collect()
+}
+
+fn route_dependents(env: &TestEnv, input_denom: &str, output_denom: &str) -> Vec<DenomPair> {
+    let res: RouteDependentsResponse = Wasm::new(&env.app)
+        .query(
+            &env.contract_address,
+            &QueryMsg::RouteDependents {
+                input_denom: input_denom.to_string(),
+                output_denom: output_denom.to_string(),
+            },
+        )
+        .unwrap();
+    res.routes
+}
+
+// setup_composed_route sets the uion -> stake route composed of
+// the stored uion -> uosmo route through pool(1) and pool(2): uosmo/stake,
+// and creates pool(4): uion/uosmo as an alternative to pool(1).
+fn setup_composed_route(env: &TestEnv) {
+    Gamm::new(&env.app)
+        .create_basic_pool(
+            &[Coin::new(1_000, "uion"), Coin::new(1_000, "uosmo")],
+            &env.owner,
+        )
+        .unwrap();
+
+    set_route(env, "uion", "uosmo", vec![hop(1, "uosmo")], false).unwrap();
+    set_route(
+        env,
+        "uion",
+        "stake",
+        vec![stored("uion", "uosmo"), hop(2, "stake")],
+        false,
+    )
+    .unwrap();
+}
+
+#[test]
+fn composed_route_is_expanded() {
+    let env = TestEnv::new();
+    setup_composed_route(&env);
+
+    assert_eq!(route_pool_ids(&env, "uion", "stake"), vec![1, 2]);
+    assert_eq!(
+        route_dependents(&env, "uion", "uosmo"),
+        vec![DenomPair {
+            input_denom: "uion".to_string(),
+            output_denom: "stake".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn changing_sub_route_is_refused_without_propagate() {
+    let env = TestEnv::new();
+    setup_composed_route(&env);
+
+    let err = set_route(&env, "uion", "uosmo", vec![hop(4, "uosmo")], false).unwrap_err();
+    assert_eq!(
+        err,
+        RunnerError::ExecuteError {
+            msg: r#"failed to execute message; message index: 0: Invalid Pool Route: "route from uion to uosmo is used by the routes uion -> stake, set propagate to update them": execute wasm contract failed"#.to_string()
+        }
+    );
+    assert_eq!(route_pool_ids(&env, "uion", "stake"), vec![1, 2]);
+}
+
+#[test]
+fn changing_sub_route_propagates() {
+    let env = TestEnv::new();
+    setup_composed_route(&env);
+
+    let res = set_route(&env, "uion", "uosmo", vec![hop(4, "uosmo")], true).unwrap();
+
+    let propagated: Vec<&str> = res
+        .events
+        .iter()
+        .filter(|event| event.ty == "wasm-route_propagated")
+        .flat_map(|event| event.attributes.iter())
+        .filter(|attribute| attribute.key == "output_denom")
+        .map(|attribute| attribute.value.as_str())
+        .collect();
+    assert_eq!(propagated, vec!["stake"]);
+    assert_eq!(route_pool_ids(&env, "uion", "stake"), vec![4, 2]);
+}
+
+#[test]
+fn composition_cycle_is_rejected() {
+    let env = TestEnv::new();
+    setup_composed_route(&env);
+
+    let err = set_route(
+        &env,
+        "uion",
+        "uosmo",
+        vec![stored("uion", "stake"), hop(2, "uosmo")],
+        true,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        RunnerError::ExecuteError {
+            msg: r#"failed to execute message; message index: 0: Invalid Pool Route: "route from uion to stake depends on the route from uion to uosmo": execute wasm contract failed"#.to_string()
+        }
+    );
+}
+
+#[test]
+fn missing_referenced_route_is_rejected() {
+    let env = TestEnv::new();
+
+    let err = set_route(
+        &env,
+        "uion",
+        "stake",
+        vec![stored("uion", "uosmo"), hop(2, "stake")],
+        false,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        RunnerError::ExecuteError {
+            msg: r#"failed to execute message; message index: 0: Invalid Pool Route: "no route from uion to uosmo is stored": execute wasm contract failed"#.to_string()
+        }
+    );
+}
+
+#[test]
+fn raw_route_replaces_composition() {
+    let env = TestEnv::new();
+    setup_composed_route(&env);
+
+    set_route(&env, "uion", "stake", vec![hop(3, "stake")], false).unwrap();
+
+    assert!(route_dependents(&env, "uion", "uosmo").is_empty());
+    set_route(&env, "uion", "uosmo", vec![hop(4, "uosmo")], false).unwrap();
+    assert_eq!(route_pool_ids(&env, "uion", "stake"), vec![3]);
+}
+
+#[test]
+fn set_routes_refuses_changing_sub_route() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    setup_composed_route(&env);
+
+    let err = wasm
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::SetRoutes {
+                routes: vec![route_entry("uion", "uosmo", &[(4, "uosmo")])],
+            },
+            &[],
+            &env.owner,
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        RunnerError::ExecuteError {
+            msg: r#"failed to execute message; message index: 0: Invalid Pool Route: "route from uion to uosmo is used by the routes uion -> stake, set propagate to update them": execute wasm contract failed"#.to_string()
+        }
+    );
+
+    // replacing the composed route in the same batch unlinks it.
+    wasm.execute(
+        &env.contract_address,
+        &ExecuteMsg::SetRoutes {
+            routes: vec![
+                route_entry("uion", "uosmo", &[(4, "uosmo")]),
+                route_entry("uion", "stake", &[(3, "stake")]),
+            ],
+        },
+        &[],
+        &env.owner,
+    )
+    .unwrap();
+    assert_eq!(route_pool_ids(&env, "uion", "stake"), vec![3]);
+    assert!(route_dependents(&env, "uion", "uosmo").is_empty());
+}
+
+#[test]
+fn removing_sub_route_removes_composed_route() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    let expires_at = Timestamp::from_seconds(env.app.get_block_time_seconds() as u64 + 60);
+    wasm.execute(
+        &env.contract_address,
+        &ExecuteMsg::SetRoute {
+            input_denom: "uion".to_string(),
+            output_denom: "uosmo".to_string(),
+            pool_route: vec![hop(1, "uosmo")],
+            bidirectional: false,
+            expires_at: Some(expires_at),
+            propagate: false,
+        },
+        &[],
+        &env.owner,
+    )
+    .unwrap();
+    set_route(
+        &env,
+        "uion",
+        "stake",
+        vec![stored("uion", "uosmo"), hop(2, "stake")],
+        false,
+    )
+    .unwrap();
+
+    env.app.increase_time(60);
+    let res = wasm
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::RemoveExpiredRoutes {
+                start_after: None,
+                limit: None,
+            },
+            &[],
+            &env.owner,
+        )
+        .unwrap();
+
+    assert!(res
+        .events
+        .iter()
+        .any(|event| event.ty == "wasm-dependent_route_removed"));
+    assert!(route_dependents(&env, "uion", "uosmo").is_empty());
+    assert!(wasm
+        .query::<QueryMsg, GetRouteResponse>(
+            &env.contract_address,
+            &QueryMsg::GetRoute {
+                input_denom: "uion".to_string(),
+                output_denom: "stake".to_string(),
+            },
+        )
+        .is_err());
+}
+
+#[test]
+fn proposed_sub_route_propagates() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    setup_composed_route(&env);
+    wasm.execute(
+        &env.contract_address,
+        &ExecuteMsg::UpdateConfig(ConfigUpdate {
+            route_timelock_seconds: Some(60),
+            ..Default::default()
+        }),
+        &[],
+        &env.owner,
+    )
+    .unwrap();
+
+    wasm.execute(
+        &env.contract_address,
+        &ExecuteMsg::ProposeRoute {
+            input_denom: "uion".to_string(),
+            output_denom: "uosmo".to_string(),
+            pool_route: vec![SwapAmountInRoute {
+                pool_id: 4,
+                token_out_denom: "uosmo".to_string(),
+            }],
+            bidirectional: false,
+            expires_at: None,
+            propagate: true,
+        },
+        &[],
+        &env.owner,
+    )
+    .unwrap();
+    env.app.increase_time(60);
+    wasm.execute(
+        &env.contract_address,
+        &ExecuteMsg::ExecuteProposedRoute {
+            input_denom: "uion".to_string(),
+            output_denom: "uosmo".to_string(),
+        },
+        &[],
+        &env.owner,
+    )
+    .unwrap();
+
+    assert_eq!(route_pool_ids(&env, "uion", "stake"), vec![4, 2]);
+}
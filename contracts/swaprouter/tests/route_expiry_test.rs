@@ -0,0 +1,203 @@
+mod test_env;
+
+use cosmwasm_std::{Coin, Timestamp};
+use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountInRoute;
+use osmosis_test_tube::{Module, RunnerError, Wasm};
+use swaprouter::msg::{
+    ExecuteMsg, ExportRoutesResponse, GetRouteResponse, ListRoutesResponse, QueryMsg, SwapType,
+};
+use swaprouter::state::RouteSegment;
+use test_env::*;
+
+const EXPIRY_SECONDS: u64 = 60;
+
+fn set_expiring_route(env: &TestEnv, expires_at: Timestamp) -> Result<(), RunnerError> {
+    Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::SetRoute {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+                pool_route: vec![RouteSegment::Hop(SwapAmountInRoute {
+                    pool_id: 1,
+                    token_out_denom: "uion".to_string(),
+                })],
+                bidirectional: false,
+                expires_at: Some(expires_at),
+                propagate: false,
+            },
+            &[],
+            &env.owner,
+        )
+        .map(|_| ())
+}
+
+fn list_routes(env: &TestEnv) -> ListRoutesResponse {
+    Wasm::new(&env.app)
+        .query(
+            &env.contract_address,
+            &QueryMsg::ListRoutes {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap()
+}
+
+fn block_time(env: &TestEnv) -> Timestamp {
+    Timestamp::from_seconds(env.app.get_block_time_seconds() as u64)
+}
+
+#[test]
+fn expired_route_is_treated_as_missing() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    let expires_at = block_time(&env).plus_seconds(EXPIRY_SECONDS);
+    set_expiring_route(&env, expires_at).unwrap();
+
+    let sender = env
+        .app
+        .init_account(&[Coin::new(1_000_000_000, "uosmo")])
+        .unwrap();
+    env.swap_default_route(&sender, 1000);
+
+    let res = list_routes(&env);
+    assert_eq!(res.routes.len(), 1);
+    assert_eq!(res.routes[0].expires_at, Some(expires_at));
+    assert!(!res.routes[0].expired);
+
+    env.app.increase_time(EXPIRY_SECONDS);
+
+    let err = wasm
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::Swap {
+                input_coin: Coin::new(1000, "uosmo"),
+                output_denom: "uion".to_string(),
+                swap_type: SwapType::MinOutputAmount(1u128.into()),
+                deadline: None,
+                execution_mode: None,
+            },
+            &[Coin::new(1000, "uosmo")],
+            &sender,
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        RunnerError::ExecuteError {
+            msg: "failed to execute message; message index: 0: alloc::vec::Vec<swaprouter::state::RouteHop> not found: execute wasm contract failed".to_string()
+        }
+    );
+
+    let res = list_routes(&env);
+    assert_eq!(res.routes.len(), 1);
+    assert!(res.routes[0].expired);
+}
+
+#[test]
+fn expired_route_is_neither_queried_nor_exported() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    let expires_at = block_time(&env).plus_seconds(EXPIRY_SECONDS);
+    set_expiring_route(&env, expires_at).unwrap();
+    let get_route = QueryMsg::GetRoute {
+        input_denom: "uosmo".to_string(),
+        output_denom: "uion".to_string(),
+    };
+
+    wasm.query::<QueryMsg, GetRouteResponse>(&env.contract_address, &get_route)
+        .unwrap();
+    let res: ExportRoutesResponse = wasm
+        .query(&env.contract_address, &QueryMsg::ExportRoutes {})
+        .unwrap();
+    assert_eq!(res.routes.len(), 1);
+    assert_eq!(res.routes[0].expires_at, Some(expires_at));
+
+    env.app.increase_time(EXPIRY_SECONDS);
+
+    assert!(wasm
+        .query::<QueryMsg, GetRouteResponse>(&env.contract_address, &get_route)
+        .is_err());
+    let res: ExportRoutesResponse = wasm
+        .query(&env.contract_address, &QueryMsg::ExportRoutes {})
+        .unwrap();
+    assert!(res.routes.is_empty());
+}
+
+#[test]
+fn anyone_can_remove_expired_routes() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    env.setup_default_route();
+    set_expiring_route(&env, block_time(&env).plus_seconds(EXPIRY_SECONDS)).unwrap();
+    wasm.execute(
+        &env.contract_address,
+        &ExecuteMsg::SetRoute {
+            input_denom: "stake".to_string(),
+            output_denom: "uion".to_string(),
+            pool_route: vec![RouteSegment::Hop(SwapAmountInRoute {
+                pool_id: 3,
+                token_out_denom: "uion".to_string(),
+            })],
+            bidirectional: false,
+            expires_at: Some(block_time(&env).plus_seconds(10 * EXPIRY_SECONDS)),
+            propagate: false,
+        },
+        &[],
+        &env.owner,
+    )
+    .unwrap();
+
+    env.app.increase_time(EXPIRY_SECONDS);
+
+    let anyone = env
+        .app
+        .init_account(&[Coin::new(1_000_000_000, "uosmo")])
+        .unwrap();
+    let res = wasm
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::RemoveExpiredRoutes {
+                start_after: None,
+                limit: None,
+            },
+            &[],
+            &anyone,
+        )
+        .unwrap();
+
+    let expired: Vec<(String, String)> = res
+        .events
+        .iter()
+        .filter(|event| event.ty == "wasm-route_expired")
+        .map(|event| {
+            let value = |key: &str| {
+                event
+                    .attributes
+                    .iter()
+                    .find(|attribute| attribute.key == key)
+                    .map(|attribute| attribute.value.clone())
+                    .unwrap()
+            };
+            (value("input_denom"), value("output_denom"))
+        })
+        .collect();
+    assert_eq!(expired, vec![("uosmo".to_string(), "uion".to_string())]);
+
+    let res = list_routes(&env);
+    assert_eq!(res.routes.len(), 1);
+    assert_eq!(res.routes[0].input_denom, "stake");
+    assert!(!res.routes[0].expired);
+}
+
+#[test]
+fn route_expiring_before_now_is_rejected() {
+    let env = TestEnv::new();
+
+    let err = set_expiring_route(&env, block_time(&env)).unwrap_err();
+    assert!(
+        format!("{:?}", err).contains("route expires at"),
+        "{:?}",
+        err
+    );
+}
@@ -0,0 +1,288 @@
+mod test_env;
+
+use cosmwasm_std::Coin;
+use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountInRoute;
+use osmosis_test_tube::{Gamm, Module, RunnerError, Wasm};
+use swaprouter::msg::{
+    ConfigUpdate, ExecuteMsg, GetRouteResponse, QueryMsg, RouteHealthEntry, RouteHealthResponse,
+};
+use swaprouter::state::RouteSegment;
+use test_env::*;
+
+// setup_routes sets uosmo -> uion through pool(1) and
+// uion -> uosmo through the shallow pool(4): uosmo/uion.
+fn setup_routes(env: &TestEnv) {
+    let gamm = Gamm::new(&env.app);
+    let wasm = Wasm::new(&env.app);
+    gamm.create_basic_pool(
+        &[Coin::new(1_000, "uion"), Coin::new(1_000, "uosmo")],
+        &env.owner,
+    )
+    .unwrap();
+
+    for (input_denom, output_denom, pool_id) in [("uosmo", "uion", 1), ("uion", "uosmo", 4)] {
+        wasm.execute(
+            &env.contract_address,
+            &ExecuteMsg::SetRoute {
+                input_denom: input_denom.to_string(),
+                output_denom: output_denom.to_string(),
+                pool_route: vec![RouteSegment::Hop(SwapAmountInRoute {
+                    pool_id,
+                    token_out_denom: output_denom.to_string(),
+                })],
+                bidirectional: false,
+                expires_at: None,
+                propagate: false,
+            },
+            &[],
+            &env.owner,
+        )
+        .unwrap();
+    }
+
+    // routes through pool(4) are too shallow from now on.
+    wasm.execute(
+        &env.contract_address,
+        &ExecuteMsg::UpdateConfig(ConfigUpdate {
+            min_pool_liquidity: Some(10_000u128.into()),
+            ..Default::default()
+        }),
+        &[],
+        &env.owner,
+    )
+    .unwrap();
+}
+
+#[test]
+fn route_health_reports_unhealthy_routes() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    setup_routes(&env);
+
+    let res: RouteHealthResponse = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::RouteHealth {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+
+    assert_eq!(
+        res.routes,
+        vec![
+            RouteHealthEntry {
+                input_denom: "uion".to_string(),
+                output_denom: "uosmo".to_string(),
+                healthy: false,
+                reason: Some(
+                    "pool id 4 holds 1000uion, less than the minimum liquidity of 10000"
+                        .to_string()
+                ),
+                suspended: false,
+            },
+            RouteHealthEntry {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+                healthy: true,
+                reason: None,
+                suspended: false,
+            },
+        ]
+    );
+}
+
+// prune executes `PruneInvalidRoutes` as a new account and returns the
+// (event type, input_denom, output_denom) of its route events.
+fn prune(env: &TestEnv) -> Vec<(String, String, String)> {
+    let wasm = Wasm::new(&env.app);
+    let anyone = env
+        .app
+        .init_account(&[Coin::new(1_000_000_000_000, "uosmo")])
+        .unwrap();
+    let res = wasm
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::PruneInvalidRoutes {
+                start_after: None,
+                limit: None,
+            },
+            &[],
+            &anyone,
+        )
+        .unwrap();
+
+    res.events
+        .iter()
+        .filter(|event| event.ty.starts_with("wasm-route_"))
+        .map(|event| {
+            let attribute = |key: &str| {
+                event
+                    .attributes
+                    .iter()
+                    .find(|attribute| attribute.key == key)
+                    .unwrap()
+                    .value
+                    .clone()
+            };
+            (
+                event.ty.clone(),
+                attribute("input_denom"),
+                attribute("output_denom"),
+            )
+        })
+        .collect()
+}
+
+fn get_route(
+    env: &TestEnv,
+    input_denom: &str,
+    output_denom: &str,
+) -> Result<GetRouteResponse, RunnerError> {
+    Wasm::new(&env.app).query::<QueryMsg, GetRouteResponse>(
+        &env.contract_address,
+        &QueryMsg::GetRoute {
+            input_denom: input_denom.to_string(),
+            output_denom: output_denom.to_string(),
+        },
+    )
+}
+
+fn set_min_pool_liquidity(env: &TestEnv, min_pool_liquidity: u128) {
+    Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::UpdateConfig(ConfigUpdate {
+                min_pool_liquidity: Some(min_pool_liquidity.into()),
+                ..Default::default()
+            }),
+            &[],
+            &env.owner,
+        )
+        .unwrap();
+}
+
+#[test]
+fn anyone_can_prune_invalid_routes() {
+    let env = TestEnv::new();
+    setup_routes(&env);
+
+    assert_eq!(
+        prune(&env),
+        vec![(
+            "wasm-route_pruned".to_string(),
+            "uion".to_string(),
+            "uosmo".to_string()
+        )]
+    );
+    assert!(get_route(&env, "uion", "uosmo").is_err());
+    assert!(get_route(&env, "uosmo", "uion").is_ok());
+
+    // a suspended route is kept and only reported once.
+    let res: RouteHealthResponse = Wasm::new(&env.app)
+        .query(
+            &env.contract_address,
+            &QueryMsg::RouteHealth {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert!(res.routes[0].suspended);
+    assert!(prune(&env).is_empty());
+
+    // the route is restored once it passes validation again.
+    set_min_pool_liquidity(&env, 0);
+    assert_eq!(
+        prune(&env),
+        vec![(
+            "wasm-route_restored".to_string(),
+            "uion".to_string(),
+            "uosmo".to_string()
+        )]
+    );
+    assert!(get_route(&env, "uion", "uosmo").is_ok());
+}
+
+#[test]
+fn pruning_a_route_keeps_the_routes_composed_of_it() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    setup_routes(&env);
+    set_min_pool_liquidity(&env, 0);
+
+    // stake -> uosmo through pool(3): stake/uion and the route uion -> uosmo.
+    wasm.execute(
+        &env.contract_address,
+        &ExecuteMsg::SetRoute {
+            input_denom: "stake".to_string(),
+            output_denom: "uosmo".to_string(),
+            pool_route: vec![
+                RouteSegment::Hop(SwapAmountInRoute {
+                    pool_id: 3,
+                    token_out_denom: "uion".to_string(),
+                }),
+                RouteSegment::Route {
+                    input_denom: "uion".to_string(),
+                    output_denom: "uosmo".to_string(),
+                },
+            ],
+            bidirectional: false,
+            expires_at: None,
+            propagate: false,
+        },
+        &[],
+        &env.owner,
+    )
+    .unwrap();
+
+    // both routes swap through the shallow pool(4), each is suspended on its own.
+    set_min_pool_liquidity(&env, 10_000);
+    let events = |ty: &str| {
+        vec![
+            (ty.to_string(), "stake".to_string(), "uosmo".to_string()),
+            (ty.to_string(), "uion".to_string(), "uosmo".to_string()),
+        ]
+    };
+    assert_eq!(prune(&env), events("wasm-route_pruned"));
+    assert!(get_route(&env, "stake", "uosmo").is_err());
+
+    set_min_pool_liquidity(&env, 0);
+    assert_eq!(prune(&env), events("wasm-route_restored"));
+    assert!(get_route(&env, "stake", "uosmo").is_ok());
+}
+
+#[test]
+fn owner_can_restore_suspended_route() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    setup_routes(&env);
+    prune(&env);
+
+    let restore = ExecuteMsg::RestoreRoute {
+        input_denom: "uion".to_string(),
+        output_denom: "uosmo".to_string(),
+    };
+    let anyone = env
+        .app
+        .init_account(&[Coin::new(1_000_000_000_000, "uosmo")])
+        .unwrap();
+    let err = wasm
+        .execute(&env.contract_address, &restore, &[], &anyone)
+        .unwrap_err();
+    assert!(format!("{:?}", err).contains("Unauthorized"), "{:?}", err);
+
+    wasm.execute(&env.contract_address, &restore, &[], &env.owner)
+        .unwrap();
+    assert!(get_route(&env, "uion", "uosmo").is_ok());
+
+    let err = wasm
+        .execute(&env.contract_address, &restore, &[], &env.owner)
+        .unwrap_err();
+    assert!(
+        format!("{:?}", err).contains("route from uion to uosmo is not suspended"),
+        "{:?}",
+        err
+    );
+}
@@ -0,0 +1,111 @@
+mod test_env;
+
+use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountInRoute;
+use osmosis_test_tube::{Account, Module, Wasm};
+use swaprouter::msg::{ExecuteMsg, QueryMsg, RouteHistoryResponse};
+use swaprouter::state::RouteSegment;
+use test_env::*;
+
+fn route_history(env: &TestEnv, start_after: Option<u64>) -> RouteHistoryResponse {
+    Wasm::new(&env.app)
+        .query(
+            &env.contract_address,
+            &QueryMsg::RouteHistory {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+                start_after,
+                limit: None,
+            },
+        )
+        .unwrap()
+}
+
+#[test]
+fn route_changes_are_recorded() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    env.setup_default_route();
+
+    let res = wasm
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::SetRoute {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+                pool_route: vec![RouteSegment::Hop(SwapAmountInRoute {
+                    pool_id: 1,
+                    token_out_denom: "uion".to_string(),
+                })],
+                bidirectional: false,
+                expires_at: None,
+                propagate: false,
+            },
+            &[],
+            &env.owner,
+        )
+        .unwrap();
+
+    let previous_pool_ids = res
+        .events
+        .iter()
+        .filter(|event| event.ty == "wasm")
+        .flat_map(|event| event.attributes.iter())
+        .find(|attribute| attribute.key == "previous_pool_ids")
+        .map(|attribute| attribute.value.clone());
+    assert_eq!(previous_pool_ids, Some("2,3".to_string()));
+
+    let res = route_history(&env, None);
+    let versions: Vec<(u64, Vec<u64>, Vec<u64>)> = res
+        .changes
+        .iter()
+        .map(|entry| {
+            (
+                entry.version,
+                entry
+                    .change
+                    .previous_route
+                    .iter()
+                    .map(|hop| hop.pool_id)
+                    .collect(),
+                entry
+                    .change
+                    .new_route
+                    .iter()
+                    .map(|hop| hop.pool_id)
+                    .collect(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        versions,
+        vec![(1, vec![], vec![2, 3]), (2, vec![2, 3], vec![1])]
+    );
+    for entry in &res.changes {
+        assert_eq!(entry.change.changed_by.as_str(), env.owner.address());
+    }
+
+    let res = route_history(&env, Some(1));
+    assert_eq!(res.changes.len(), 1);
+    assert_eq!(res.changes[0].version, 2);
+}
+
+#[test]
+fn route_removal_is_recorded() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    env.setup_default_route();
+
+    wasm.execute(
+        &env.contract_address,
+        &ExecuteMsg::DisablePool { pool_id: 2 },
+        &[],
+        &env.owner,
+    )
+    .unwrap();
+
+    let res = route_history(&env, Some(1));
+    assert_eq!(res.changes.len(), 1);
+    assert_eq!(res.changes[0].version, 2);
+    assert_eq!(res.changes[0].change.previous_route.len(), 2);
+    assert!(res.changes[0].change.new_route.is_empty());
+}
@@ -0,0 +1,65 @@
+mod test_env;
+use cosmwasm_std::Coin;
+use osmosis_test_tube::{Module, Wasm};
+use swaprouter::msg::{PairStatsResponse, QueryMsg, RouterTwapResponse};
+use test_env::*;
+
+#[test]
+fn router_twap_reflects_execution_price() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    let sender = env
+        .app
+        .init_account(&[Coin::new(1_000_000_000_000, "uosmo")])
+        .unwrap();
+
+    env.setup_default_route();
+    env.swap_default_route(&sender, 1000);
+    env.app.increase_time(60);
+
+    let PairStatsResponse { stats } = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::PairStats {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+            },
+        )
+        .unwrap();
+
+    // a single swap inside the window is the only price in effect.
+    let RouterTwapResponse { twap } = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::RouterTwap {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+                window_seconds: 30,
+            },
+        )
+        .unwrap();
+    assert_eq!(twap, stats.last_execution_price);
+}
+
+#[test]
+fn router_twap_without_enough_history_fails() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    let sender = env
+        .app
+        .init_account(&[Coin::new(1_000_000_000_000, "uosmo")])
+        .unwrap();
+
+    env.setup_default_route();
+    env.swap_default_route(&sender, 1000);
+
+    let res = wasm.query::<QueryMsg, RouterTwapResponse>(
+        &env.contract_address,
+        &QueryMsg::RouterTwap {
+            input_denom: "uosmo".to_string(),
+            output_denom: "uion".to_string(),
+            window_seconds: 3600,
+        },
+    );
+    assert!(res.is_err());
+}
@@ -1,8 +1,12 @@
 mod test_env;
 use cosmwasm_std::Coin;
-use osmosis_std::types::osmosis::gamm::v1beta1::SwapAmountInRoute;
-use osmosis_testing::{Module, RunnerError, Wasm};
-use swaprouter::msg::{ExecuteMsg, GetRouteResponse, QueryMsg};
+use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountInRoute;
+use osmosis_test_tube::{Gamm, Module, RunnerError, Wasm};
+use swaprouter::msg::{
+    ConfigUpdate, ExecuteMsg, GetRouteResponse, QueryMsg, SwapHistoryResponse,
+    ValidateRouteResponse,
+};
+use swaprouter::state::{PoolType, RouteHop, RouteSegment};
 use test_env::*;
 
 test_set_route!(
@@ -13,10 +17,13 @@ test_set_route!(
     msg = ExecuteMsg::SetRoute {
         input_denom: "uosmo".to_string(),
         output_denom: "uion".to_string(),
-        pool_route: vec![SwapAmountInRoute {
+        pool_route: vec![RouteSegment::Hop(SwapAmountInRoute {
             pool_id: 1,
             token_out_denom: "uion".to_string(),
-        }],
+        })],
+        bidirectional: false,
+        expires_at: None,
+        propagate: false,
     }
 );
 
@@ -28,10 +35,13 @@ test_set_route!(
     msg = ExecuteMsg::SetRoute {
         input_denom: "uosmo".to_string(),
         output_denom: "uion".to_string(),
-        pool_route: vec![SwapAmountInRoute {
+        pool_route: vec![RouteSegment::Hop(SwapAmountInRoute {
             pool_id: 1,
             token_out_denom: "uion".to_string(),
-        }],
+        })],
+        bidirectional: false,
+        expires_at: None,
+        propagate: false,
     }
 );
 
@@ -44,15 +54,18 @@ test_set_route!(
         input_denom: "uosmo".to_string(),
         output_denom: "uion".to_string(),
         pool_route: vec![
-            SwapAmountInRoute {
+            RouteSegment::Hop(SwapAmountInRoute {
                 pool_id: 2, // stake/uosmo
                 token_out_denom: "stake".to_string(),
-            },
-            SwapAmountInRoute {
+            }),
+            RouteSegment::Hop(SwapAmountInRoute {
                 pool_id: 3, // stake/uion
                 token_out_denom: "uion".to_string(),
-            }
+            })
         ],
+        bidirectional: false,
+        expires_at: None,
+        propagate: false,
     }
 );
 
@@ -60,18 +73,20 @@ test_set_route!(
     output_denom_that_does_not_ending_pool_route
     should failed_with
     r#"Invalid Pool Route: "last denom doesn't match": execute wasm contract failed"#,
-    // r#"Invalid Pool Route: "denom uosmo is not in pool id 1": execute wasm contract failed"#,
 
     sender = Owner,
     msg = ExecuteMsg::SetRoute {
         input_denom: "uosmo".to_string(),
-        output_denom: "uion".to_string(),
+        output_denom: "stake".to_string(),
         pool_route: vec![
-            SwapAmountInRoute {
+            RouteSegment::Hop(SwapAmountInRoute {
                 pool_id: 1, // uosmo/uion
-                token_out_denom: "uosmo".to_string(),
-            },
+                token_out_denom: "uion".to_string(),
+            }),
         ],
+        bidirectional: false,
+        expires_at: None,
+        propagate: false,
     }
 );
 
@@ -85,57 +100,60 @@ test_set_route!(
         input_denom: "stake".to_string(),
         output_denom: "uion".to_string(),
         pool_route: vec![
-            SwapAmountInRoute {
+            RouteSegment::Hop(SwapAmountInRoute {
                 pool_id: 1, // uosmo/uion
                 token_out_denom: "uion".to_string(),
-            },
+            }),
         ],
+        bidirectional: false,
+        expires_at: None,
+        propagate: false,
     }
 );
 
 test_set_route!(
     pool_does_not_have_output_asset
     should failed_with
-    r#"Invalid Pool Route: "denom uosmo is not in pool id 1": execute wasm contract failed"#,
-    // confusing error message from chain, should state that:
-    // > `denom stake is not in pool id 1": execute wasm contract failed`
-    // instead.
+    r#"Invalid Pool Route: "denom stake is not in pool id 1": execute wasm contract failed"#,
 
     sender = Owner,
     msg = ExecuteMsg::SetRoute {
         input_denom: "uosmo".to_string(),
         output_denom: "stake".to_string(),
         pool_route: vec![
-            SwapAmountInRoute {
+            RouteSegment::Hop(SwapAmountInRoute {
                 pool_id: 1, // uosmo/uion
                 token_out_denom: "stake".to_string(),
-            },
+            }),
         ],
+        bidirectional: false,
+        expires_at: None,
+        propagate: false,
     }
 );
 
 test_set_route!(
     intermediary_pool_does_not_have_output_asset
     should failed_with
-    r#"Invalid Pool Route: "denom uosmo is not in pool id 1": execute wasm contract failed"#,
-    // confusing error message from chain, should state that:
-    // > `denom foocoin is not in pool id 1": execute wasm contract failed`
-    // instead.
+    r#"Invalid Pool Route: "denom foocoin is not in pool id 1": execute wasm contract failed"#,
 
     sender = Owner,
     msg = ExecuteMsg::SetRoute {
         input_denom: "uosmo".to_string(),
         output_denom: "stake".to_string(),
         pool_route: vec![
-            SwapAmountInRoute {
+            RouteSegment::Hop(SwapAmountInRoute {
                 pool_id: 1, // uosmo/uion
                 token_out_denom: "foocoin".to_string(),
-            },
-            SwapAmountInRoute {
+            }),
+            RouteSegment::Hop(SwapAmountInRoute {
                 pool_id: 2, // stake/uosmo
                 token_out_denom: "stake".to_string(),
-            },
+            }),
         ],
+        bidirectional: false,
+        expires_at: None,
+        propagate: false,
     }
 );
 
@@ -149,15 +167,18 @@ test_set_route!(
         input_denom: "uosmo".to_string(),
         output_denom: "stake".to_string(),
         pool_route: vec![
-            SwapAmountInRoute {
+            RouteSegment::Hop(SwapAmountInRoute {
                 pool_id: 1, // uosmo/uion
                 token_out_denom: "uion".to_string(),
-            },
-            SwapAmountInRoute {
+            }),
+            RouteSegment::Hop(SwapAmountInRoute {
                 pool_id: 2, // stake/uosmo
                 token_out_denom: "stake".to_string(),
-            },
+            }),
         ],
+        bidirectional: false,
+        expires_at: None,
+        propagate: false,
     }
 );
 
@@ -171,16 +192,282 @@ test_set_route!(
         input_denom: "uosmo".to_string(),
         output_denom: "stake".to_string(),
         pool_route: vec![
-            SwapAmountInRoute {
+            RouteSegment::Hop(SwapAmountInRoute {
                 pool_id: 3, // stake/uion
                 token_out_denom: "uion".to_string(),
-            },
+            }),
         ],
+        bidirectional: false,
+        expires_at: None,
+        propagate: false,
     }
 );
 
+test_set_route!(
+    route_that_revisits_a_denom
+    should failed_with
+    r#"Invalid Pool Route: "denom uosmo is visited more than once at pool id 2": execute wasm contract failed"#,
+
+    sender = Owner,
+    msg = ExecuteMsg::SetRoute {
+        input_denom: "uosmo".to_string(),
+        output_denom: "uion".to_string(),
+        pool_route: vec![
+            RouteSegment::Hop(SwapAmountInRoute {
+                pool_id: 1, // uosmo/uion
+                token_out_denom: "uion".to_string(),
+            }),
+            RouteSegment::Hop(SwapAmountInRoute {
+                pool_id: 3, // stake/uion
+                token_out_denom: "stake".to_string(),
+            }),
+            RouteSegment::Hop(SwapAmountInRoute {
+                pool_id: 2, // stake/uosmo
+                token_out_denom: "uosmo".to_string(),
+            }),
+        ],
+        bidirectional: false,
+        expires_at: None,
+        propagate: false,
+    }
+);
+
+test_set_route!(
+    route_that_reuses_a_pool
+    should failed_with
+    r#"Invalid Pool Route: "pool id 1 is used more than once": execute wasm contract failed"#,
+
+    sender = Owner,
+    msg = ExecuteMsg::SetRoute {
+        input_denom: "uosmo".to_string(),
+        output_denom: "uosmo".to_string(),
+        pool_route: vec![
+            RouteSegment::Hop(SwapAmountInRoute {
+                pool_id: 1, // uosmo/uion
+                token_out_denom: "uion".to_string(),
+            }),
+            RouteSegment::Hop(SwapAmountInRoute {
+                pool_id: 1, // uosmo/uion
+                token_out_denom: "uosmo".to_string(),
+            }),
+        ],
+        bidirectional: false,
+        expires_at: None,
+        propagate: false,
+    }
+);
+
+#[test]
+fn route_with_more_hops_than_maximum() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    update_config(
+        &env,
+        ConfigUpdate {
+            max_route_hops: Some(1),
+            ..Default::default()
+        },
+    );
+
+    let err = wasm
+        .execute(&env.contract_address, &default_route_msg(), &[], &env.owner)
+        .unwrap_err();
+    assert_eq!(
+        err,
+        RunnerError::ExecuteError {
+            msg: r#"failed to execute message; message index: 0: Invalid Pool Route: "route has 2 hops, more than the maximum of 1": execute wasm contract failed"#.to_string()
+        }
+    );
+}
+
+#[test]
+fn route_through_pool_below_minimum_liquidity() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    update_config(
+        &env,
+        ConfigUpdate {
+            min_pool_liquidity: Some(100_000_001u128.into()),
+            ..Default::default()
+        },
+    );
+
+    let err = wasm
+        .execute(&env.contract_address, &default_route_msg(), &[], &env.owner)
+        .unwrap_err();
+    assert_eq!(
+        err,
+        RunnerError::ExecuteError {
+            msg: r#"failed to execute message; message index: 0: Invalid Pool Route: "pool id 2 holds 100000000uosmo, less than the minimum liquidity of 100000001": execute wasm contract failed"#.to_string()
+        }
+    );
+}
+
+#[test]
+fn validate_route_dry_runs_validation() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+
+    let validate = |output_denom: &str| -> ValidateRouteResponse {
+        wasm.query(
+            &env.contract_address,
+            &QueryMsg::ValidateRoute {
+                input_denom: "uosmo".to_string(),
+                output_denom: output_denom.to_string(),
+                pool_route: vec![SwapAmountInRoute {
+                    pool_id: 1,
+                    token_out_denom: "uion".to_string(),
+                }],
+            },
+        )
+        .unwrap()
+    };
+
+    assert_eq!(
+        validate("uion"),
+        ValidateRouteResponse {
+            valid: true,
+            reason: None,
+        }
+    );
+    assert_eq!(
+        validate("stake"),
+        ValidateRouteResponse {
+            valid: false,
+            reason: Some("last denom doesn't match".to_string()),
+        }
+    );
+
+    // dry-run does not save the route.
+    let res = wasm.query::<QueryMsg, GetRouteResponse>(
+        &env.contract_address,
+        &QueryMsg::GetRoute {
+            input_denom: "uosmo".to_string(),
+            output_denom: "uion".to_string(),
+        },
+    );
+    assert!(res.is_err());
+}
+
+#[test]
+fn route_is_stored_with_pool_types() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    env.setup_default_route();
+
+    let res: GetRouteResponse = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::GetRoute {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+            },
+        )
+        .unwrap();
+
+    assert_eq!(
+        res.pool_route,
+        vec![
+            RouteHop {
+                pool_id: 2,
+                pool_type: PoolType::Balancer,
+                token_out_denom: "stake".to_string(),
+            },
+            RouteHop {
+                pool_id: 3,
+                pool_type: PoolType::Balancer,
+                token_out_denom: "uion".to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn bidirectional_route_sets_reverse_route() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+
+    let msg = match default_route_msg() {
+        ExecuteMsg::SetRoute {
+            input_denom,
+            output_denom,
+            pool_route,
+            ..
+        } => ExecuteMsg::SetRoute {
+            input_denom,
+            output_denom,
+            pool_route,
+            bidirectional: true,
+            expires_at: None,
+            propagate: false,
+        },
+        _ => unreachable!(),
+    };
+    wasm.execute(&env.contract_address, &msg, &[], &env.owner)
+        .unwrap();
+
+    let res: GetRouteResponse = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::GetRoute {
+                input_denom: "uion".to_string(),
+                output_denom: "uosmo".to_string(),
+            },
+        )
+        .unwrap();
+
+    assert_eq!(
+        res.pool_route,
+        vec![
+            RouteHop {
+                pool_id: 3,
+                pool_type: PoolType::Balancer,
+                token_out_denom: "stake".to_string(),
+            },
+            RouteHop {
+                pool_id: 2,
+                pool_type: PoolType::Balancer,
+                token_out_denom: "uosmo".to_string(),
+            },
+        ]
+    );
+}
+
 // ======= helpers ========
 
+// default_route_msg sets the uosmo -> uion route
+// through pool(2): uosmo/stake -> pool(3): stake/uion
+fn default_route_msg() -> ExecuteMsg {
+    ExecuteMsg::SetRoute {
+        input_denom: "uosmo".to_string(),
+        output_denom: "uion".to_string(),
+        pool_route: vec![
+            RouteSegment::Hop(SwapAmountInRoute {
+                pool_id: 2,
+                token_out_denom: "stake".to_string(),
+            }),
+            RouteSegment::Hop(SwapAmountInRoute {
+                pool_id: 3,
+                token_out_denom: "uion".to_string(),
+            }),
+        ],
+        bidirectional: false,
+        expires_at: None,
+        propagate: false,
+    }
+}
+
+fn update_config(env: &TestEnv, update: ConfigUpdate) {
+    Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::UpdateConfig(update),
+            &[],
+            &env.owner,
+        )
+        .unwrap();
+}
+
 #[macro_export]
 macro_rules! test_set_route {
     ($test_name:ident should succeed, sender = Owner, msg = $msg:expr) => {
@@ -271,3 +558,79 @@ fn test_set_route_failed_case(sender: Sender, msg: ExecuteMsg, expected_error: &
         panic!("unexpected error: {:?}", err);
     }
 }
+
+#[test]
+fn route_mixing_stableswap_and_balancer_pools() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+
+    // pool(4): stake/uion stableswap pool next to the balancer pools.
+    Gamm::new(&env.app)
+        .create_stable_swap_pool(
+            &[
+                Coin::new(100_000_000, "stake"),
+                Coin::new(100_000_000, "uion"),
+            ],
+            vec![1, 1],
+            &env.owner,
+        )
+        .unwrap();
+
+    wasm.execute(
+        &env.contract_address,
+        &ExecuteMsg::SetRoute {
+            input_denom: "uosmo".to_string(),
+            output_denom: "uion".to_string(),
+            pool_route: vec![
+                RouteSegment::Hop(SwapAmountInRoute {
+                    pool_id: 2,
+                    token_out_denom: "stake".to_string(),
+                }),
+                RouteSegment::Hop(SwapAmountInRoute {
+                    pool_id: 4,
+                    token_out_denom: "uion".to_string(),
+                }),
+            ],
+            bidirectional: false,
+            expires_at: None,
+            propagate: false,
+        },
+        &[],
+        &env.owner,
+    )
+    .unwrap();
+
+    let res: GetRouteResponse = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::GetRoute {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+            },
+        )
+        .unwrap();
+    let pool_types: Vec<PoolType> = res
+        .pool_route
+        .into_iter()
+        .map(|hop| hop.pool_type)
+        .collect();
+    assert_eq!(pool_types, vec![PoolType::Balancer, PoolType::Stableswap]);
+
+    let sender = env
+        .app
+        .init_account(&[Coin::new(1_000_000_000_000, "uosmo")])
+        .unwrap();
+    env.swap_default_route(&sender, 1000);
+
+    let res: SwapHistoryResponse = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::SwapHistory {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(res.records[0].record.pool_ids, vec![2, 4]);
+    assert!(!res.records[0].record.output_coin.amount.is_zero());
+}
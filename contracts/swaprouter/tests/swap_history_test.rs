@@ -0,0 +1,158 @@
+mod test_env;
+use cosmwasm_std::Coin;
+use osmosis_test_tube::{Account, Module, Wasm};
+use swaprouter::msg::{ConfigUpdate, ExecuteMsg, QueryMsg, SwapHistoryResponse};
+use test_env::*;
+
+#[test]
+fn swap_is_recorded_in_history() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    let sender = env
+        .app
+        .init_account(&[Coin::new(1_000_000_000_000, "uosmo")])
+        .unwrap();
+
+    env.setup_default_route();
+    env.swap_default_route(&sender, 1000);
+
+    let res: SwapHistoryResponse = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::SwapHistoryBySender {
+                sender: sender.address(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(res.records.len(), 1);
+
+    let record = &res.records[0].record;
+    assert_eq!(record.sender.as_str(), sender.address());
+    assert_eq!(record.input_coin, Coin::new(1000, "uosmo"));
+    assert_eq!(record.output_coin.denom, "uion");
+    assert_eq!(record.pool_ids, vec![2, 3]);
+
+    let res: SwapHistoryResponse = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::SwapHistoryByPair {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(res.records.len(), 1);
+
+    let res: SwapHistoryResponse = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::SwapHistoryByPair {
+                input_denom: "uion".to_string(),
+                output_denom: "uosmo".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert!(res.records.is_empty());
+}
+
+#[test]
+fn swap_history_is_pruned_beyond_retention() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    let sender = env
+        .app
+        .init_account(&[Coin::new(1_000_000_000_000, "uosmo")])
+        .unwrap();
+
+    env.setup_default_route();
+    wasm.execute(
+        &env.contract_address,
+        &ExecuteMsg::UpdateConfig(ConfigUpdate {
+            swap_history_retention: Some(2),
+            ..Default::default()
+        }),
+        &[],
+        &env.owner,
+    )
+    .unwrap();
+
+    for _ in 0..3 {
+        env.swap_default_route(&sender, 1000);
+    }
+
+    let res: SwapHistoryResponse = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::SwapHistory {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    let ids: Vec<u64> = res.records.iter().map(|entry| entry.id).collect();
+    assert_eq!(ids, vec![1, 2]);
+
+    // paginate past the first record
+    let res: SwapHistoryResponse = wasm
+        .query(
+            &env.contract_address,
+            &QueryMsg::SwapHistory {
+                start_after: Some(1),
+                limit: Some(1),
+            },
+        )
+        .unwrap();
+    let ids: Vec<u64> = res.records.iter().map(|entry| entry.id).collect();
+    assert_eq!(ids, vec![2]);
+}
+
+#[test]
+fn lowered_retention_is_pruned_over_several_swaps() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    let sender = env
+        .app
+        .init_account(&[Coin::new(1_000_000_000_000, "uosmo")])
+        .unwrap();
+
+    env.setup_default_route();
+    for _ in 0..12 {
+        env.swap_default_route(&sender, 1000);
+    }
+    wasm.execute(
+        &env.contract_address,
+        &ExecuteMsg::UpdateConfig(ConfigUpdate {
+            swap_history_retention: Some(1),
+            ..Default::default()
+        }),
+        &[],
+        &env.owner,
+    )
+    .unwrap();
+
+    let history_ids = || -> Vec<u64> {
+        let res: SwapHistoryResponse = wasm
+            .query(
+                &env.contract_address,
+                &QueryMsg::SwapHistory {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        res.records.iter().map(|entry| entry.id).collect()
+    };
+
+    // a single swap prunes at most 10 of the 12 expired records.
+    env.swap_default_route(&sender, 1000);
+    assert_eq!(history_ids(), vec![10, 11, 12]);
+
+    env.swap_default_route(&sender, 1000);
+    assert_eq!(history_ids(), vec![13]);
+}
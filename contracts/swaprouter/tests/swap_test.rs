@@ -1,13 +1,16 @@
 mod test_env;
 use std::str::FromStr;
 
-use cosmwasm_std::{Coin, Decimal};
-use osmosis_std::types::osmosis::gamm::v1beta1::SwapAmountInRoute;
-use osmosis_testing::cosmrs::proto::cosmwasm::wasm::v1::MsgExecuteContractResponse;
-use osmosis_testing::{
-    Account, Bank, Module, OsmosisTestApp, RunnerError, RunnerExecuteResult, SigningAccount, Wasm,
+use cosmwasm_std::{to_json_binary, Addr, Coin, CosmosMsg, Decimal, WasmMsg};
+use osmosis_std::types::cosmos::bank::v1beta1::QueryAllBalancesRequest;
+use osmosis_std::types::cosmwasm::wasm::v1::MsgExecuteContractResponse;
+use osmosis_std::types::osmosis::poolmanager::v1beta1::{MsgSwapExactAmountIn, SwapAmountInRoute};
+use osmosis_test_tube::{
+    Account, Bank, Module, OsmosisTestApp, Runner, RunnerError, RunnerExecuteResult,
+    SigningAccount, Wasm,
 };
-use swaprouter::msg::{ExecuteMsg, SwapType};
+use swaprouter::msg::{ConfigUpdate, Deadline, ExecuteMsg, ExecutionMode, SwapType};
+use swaprouter::state::{PairConfig, PriceSource, RouteSegment};
 use test_env::*;
 
 test_swap!(
@@ -18,6 +21,8 @@ test_swap!(
         input_coin: Coin::new(1000, "uosmo"),
         output_denom: "uion".to_string(),
         swap_type: SwapType::MinOutputAmount(1u128.into()),
+        deadline: None,
+        execution_mode: None,
     },
     funds: [
         Coin::new(1000, "uosmo")
@@ -32,6 +37,8 @@ test_swap!(
         input_coin: Coin::new(1000, "uosmo"),
         output_denom: "uion".to_string(),
         swap_type: SwapType::MinOutputAmount(1u128.into()),
+        deadline: None,
+        execution_mode: None,
     },
     funds: [
         Coin::new(10, "uosmo")
@@ -46,6 +53,8 @@ test_swap!(
         input_coin: Coin::new(1000, "uosmo"),
         output_denom: "uion".to_string(),
         swap_type: SwapType::MinOutputAmount(1u128.into()),
+        deadline: None,
+        execution_mode: None,
     },
     funds: [
         Coin::new(10, "uion")
@@ -60,6 +69,8 @@ test_swap!(
         input_coin: Coin::new(1000, "uosmo"),
         output_denom: "uion".to_string(),
         swap_type: SwapType::MinOutputAmount(1000000000000000000000000u128.into()),
+        deadline: None,
+        execution_mode: None,
     },
     funds: [
         Coin::new(1000, "uosmo")
@@ -68,12 +79,14 @@ test_swap!(
 
 test_swap!(
     non_existant_route should failed_with
-    "alloc::vec::Vec<osmosis_std::types::osmosis::gamm::v1beta1::SwapAmountInRoute> not found: execute wasm contract failed",
+    "alloc::vec::Vec<swaprouter::state::RouteHop> not found: execute wasm contract failed",
 
     msg = ExecuteMsg::Swap {
         input_coin: Coin::new(1000, "uion"),
         output_denom: "uosmo".to_string(),
         swap_type: SwapType::MinOutputAmount(1000000000000000000000000u128.into()),
+        deadline: None,
+        execution_mode: None,
     },
     funds: [
         Coin::new(1000, "uion")
@@ -88,12 +101,46 @@ test_swap!(
         input_coin: Coin::new(1000, "uosmo"),
         output_denom: "uion".to_string(),
         swap_type: SwapType::MaxSlippagePercentage(Decimal::from_str("5").unwrap()),
+        deadline: None,
+        execution_mode: None,
     },
     funds: [
         Coin::new(10000, "uosmo")
     ]
 );
 
+test_swap!(
+    swap_before_deadline
+    should succeed,
+
+    msg = ExecuteMsg::Swap {
+        input_coin: Coin::new(1000, "uosmo"),
+        output_denom: "uion".to_string(),
+        swap_type: SwapType::MinOutputAmount(1u128.into()),
+        deadline: Some(Deadline::AtHeight(u64::MAX)),
+        execution_mode: None,
+    },
+    funds: [
+        Coin::new(1000, "uosmo")
+    ]
+);
+
+test_swap!(
+    swap_after_deadline should failed_with
+    "Deadline Exceeded: AtHeight(1): execute wasm contract failed",
+
+    msg = ExecuteMsg::Swap {
+        input_coin: Coin::new(1000, "uosmo"),
+        output_denom: "uion".to_string(),
+        swap_type: SwapType::MinOutputAmount(1u128.into()),
+        deadline: Some(Deadline::AtHeight(1)),
+        execution_mode: None,
+    },
+    funds: [
+        Coin::new(1000, "uosmo")
+    ]
+);
+
 // ======= helpers ========
 
 #[macro_export]
@@ -164,15 +211,18 @@ fn setup_route_and_execute_swap(
         input_denom: "uosmo".to_string(),
         output_denom: "uion".to_string(),
         pool_route: vec![
-            SwapAmountInRoute {
+            RouteSegment::Hop(SwapAmountInRoute {
                 pool_id: 2,
                 token_out_denom: "stake".to_string(),
-            },
-            SwapAmountInRoute {
+            }),
+            RouteSegment::Hop(SwapAmountInRoute {
                 pool_id: 3,
                 token_out_denom: "uion".to_string(),
-            },
+            }),
         ],
+        bidirectional: false,
+        expires_at: None,
+        propagate: false,
     };
 
     // setup route by swaprouter's owner
@@ -196,7 +246,13 @@ fn assert_input_decreased_and_output_increased(
     msg: &ExecuteMsg,
 ) {
     let bank = Bank::new(app);
-    let balances = bank.query_all_balances(sender, None).unwrap().balances;
+    let balances = bank
+        .query_all_balances(&QueryAllBalancesRequest {
+            address: sender.to_string(),
+            pagination: None,
+        })
+        .unwrap()
+        .balances;
     match msg {
         ExecuteMsg::Swap {
             input_coin,
@@ -222,7 +278,7 @@ fn assert_input_decreased_and_output_increased(
 }
 
 fn get_amount(
-    balances: &Vec<osmosis_testing::cosmrs::proto::cosmos::base::v1beta1::Coin>,
+    balances: &Vec<osmosis_std::types::cosmos::base::v1beta1::Coin>,
     denom: &str,
 ) -> u128 {
     balances
@@ -233,3 +289,326 @@ fn get_amount(
         .parse::<u128>()
         .unwrap()
 }
+
+#[test]
+fn twap_based_swap_with_spot_deviation_guard() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    let sender = env
+        .app
+        .init_account(&[
+            Coin::new(INITIAL_AMOUNT, "uosmo"),
+            Coin::new(INITIAL_AMOUNT, "uion"),
+        ])
+        .unwrap();
+
+    env.setup_default_route();
+    wasm.execute(
+        &env.contract_address,
+        &ExecuteMsg::UpdateConfig(ConfigUpdate {
+            max_spot_twap_deviation_percentage: Some(Decimal::from_str("1").unwrap()),
+            ..Default::default()
+        }),
+        &[],
+        &env.owner,
+    )
+    .unwrap();
+
+    // untouched pools have spot price equal to twap.
+    let msg = ExecuteMsg::Swap {
+        input_coin: Coin::new(1000, "uosmo"),
+        output_denom: "uion".to_string(),
+        swap_type: SwapType::MaxSlippagePercentage(Decimal::from_str("5").unwrap()),
+        deadline: None,
+        execution_mode: None,
+    };
+    wasm.execute(
+        &env.contract_address,
+        &msg,
+        &[Coin::new(1000, "uosmo")],
+        &sender,
+    )
+    .unwrap();
+    assert_input_decreased_and_output_increased(&env.app, &sender.address(), &msg);
+}
+
+#[test]
+fn oracle_priced_swap_rejected_by_spot_deviation_guard() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    let sender = env
+        .app
+        .init_account(&[Coin::new(INITIAL_AMOUNT, "uosmo")])
+        .unwrap();
+
+    env.setup_default_route();
+    wasm.execute(
+        &env.contract_address,
+        &ExecuteMsg::UpdateConfig(ConfigUpdate {
+            max_spot_twap_deviation_percentage: Some(Decimal::from_str("1").unwrap()),
+            ..Default::default()
+        }),
+        &[],
+        &env.owner,
+    )
+    .unwrap();
+
+    // the oracle prices uosmo at 2 uion while the untouched 1:1 pools quote ~1 uion.
+    let oracle = env.instantiate_mock_oracle(Decimal::from_str("2").unwrap());
+    wasm.execute(
+        &env.contract_address,
+        &ExecuteMsg::SetPairConfig {
+            input_denom: "uosmo".to_string(),
+            output_denom: "uion".to_string(),
+            pair_config: PairConfig {
+                price_source: PriceSource::Oracle {
+                    contract_address: Addr::unchecked(oracle),
+                },
+                ..Default::default()
+            },
+        },
+        &[],
+        &env.owner,
+    )
+    .unwrap();
+
+    let err = wasm
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::Swap {
+                input_coin: Coin::new(1000, "uosmo"),
+                output_denom: "uion".to_string(),
+                swap_type: SwapType::MaxSlippagePercentage(Decimal::from_str("5").unwrap()),
+                deadline: None,
+                execution_mode: None,
+            },
+            &[Coin::new(1000, "uosmo")],
+            &sender,
+        )
+        .unwrap_err();
+    assert!(
+        format!("{:?}", err).contains("deviates from the oracle price 2 by more than 1%"),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn twap_based_swap_rejected_after_spot_price_skew_in_same_block() {
+    let env = TestEnv::new();
+    let sender = env
+        .app
+        .init_account(&[Coin::new(INITIAL_AMOUNT, "uosmo")])
+        .unwrap();
+
+    env.setup_default_route();
+    Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::UpdateConfig(ConfigUpdate {
+                max_spot_twap_deviation_percentage: Some(Decimal::from_str("1").unwrap()),
+                ..Default::default()
+            }),
+            &[],
+            &env.owner,
+        )
+        .unwrap();
+
+    // sandwich front-run: dump uosmo into pool(2) right before the router swap,
+    // in the same block so that the TWAP still reflects the untouched pool.
+    let skew: CosmosMsg = MsgSwapExactAmountIn {
+        sender: sender.address(),
+        routes: vec![SwapAmountInRoute {
+            pool_id: 2,
+            token_out_denom: "stake".to_string(),
+        }],
+        token_in: Some(Coin::new(50_000_000, "uosmo").into()),
+        token_out_min_amount: "1".to_string(),
+    }
+    .into();
+    let swap = WasmMsg::Execute {
+        contract_addr: env.contract_address.clone(),
+        msg: to_json_binary(&ExecuteMsg::Swap {
+            input_coin: Coin::new(1000, "uosmo"),
+            output_denom: "uion".to_string(),
+            swap_type: SwapType::MaxSlippagePercentage(Decimal::from_str("5").unwrap()),
+            deadline: None,
+            execution_mode: None,
+        })
+        .unwrap(),
+        funds: vec![Coin::new(1000, "uosmo")],
+    }
+    .into();
+
+    let err = env
+        .app
+        .execute_cosmos_msgs::<MsgExecuteContractResponse>(&[skew, swap], &sender)
+        .unwrap_err();
+    assert!(
+        format!("{:?}", err).contains("deviates from the arithmetic TWAP price"),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn step_wise_swap_reports_every_hop() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    let sender = env
+        .app
+        .init_account(&[
+            Coin::new(INITIAL_AMOUNT, "uosmo"),
+            Coin::new(INITIAL_AMOUNT, "uion"),
+        ])
+        .unwrap();
+
+    env.setup_default_route();
+
+    let msg = ExecuteMsg::Swap {
+        input_coin: Coin::new(1000, "uosmo"),
+        output_denom: "uion".to_string(),
+        swap_type: SwapType::MaxSlippagePercentage(Decimal::from_str("5").unwrap()),
+        deadline: None,
+        execution_mode: Some(ExecutionMode::StepWise {
+            hop_max_slippage_percentage: Decimal::from_str("5").unwrap(),
+        }),
+    };
+    let res = wasm
+        .execute(
+            &env.contract_address,
+            &msg,
+            &[Coin::new(1000, "uosmo")],
+            &sender,
+        )
+        .unwrap();
+    assert_input_decreased_and_output_increased(&env.app, &sender.address(), &msg);
+
+    let hop_pools: Vec<String> = res
+        .events
+        .iter()
+        .filter(|event| event.ty == "wasm-swap_hop")
+        .flat_map(|event| event.attributes.iter())
+        .filter(|attribute| attribute.key == "pool_id")
+        .map(|attribute| attribute.value.clone())
+        .collect();
+    assert_eq!(hop_pools, vec!["2", "3"]);
+}
+
+#[test]
+fn step_wise_swap_with_hop_slippage_above_hundred_percent() {
+    let env = TestEnv::new();
+    let sender = env
+        .app
+        .init_account(&[Coin::new(INITIAL_AMOUNT, "uosmo")])
+        .unwrap();
+
+    env.setup_default_route();
+
+    let err = Wasm::new(&env.app)
+        .execute(
+            &env.contract_address,
+            &ExecuteMsg::Swap {
+                input_coin: Coin::new(1000, "uosmo"),
+                output_denom: "uion".to_string(),
+                swap_type: SwapType::MinOutputAmount(1u128.into()),
+                deadline: None,
+                execution_mode: Some(ExecutionMode::StepWise {
+                    hop_max_slippage_percentage: Decimal::from_str("101").unwrap(),
+                }),
+            },
+            &[Coin::new(1000, "uosmo")],
+            &sender,
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        RunnerError::ExecuteError {
+            msg: "failed to execute message; message index: 0: Invalid Slippage Percentage: 101% is more than 100%: execute wasm contract failed".to_string()
+        }
+    );
+}
+
+#[test]
+fn best_effort_swap_refunds_input_on_failure() {
+    let env = TestEnv::new();
+    let wasm = Wasm::new(&env.app);
+    let sender = env
+        .app
+        .init_account(&[
+            Coin::new(INITIAL_AMOUNT, "uosmo"),
+            Coin::new(INITIAL_AMOUNT, "uion"),
+        ])
+        .unwrap();
+
+    env.setup_default_route();
+
+    // minimum output can not be met, so the swap itself fails.
+    let msg = ExecuteMsg::Swap {
+        input_coin: Coin::new(1000, "uosmo"),
+        output_denom: "uion".to_string(),
+        swap_type: SwapType::MinOutputAmount(1000000000000000000u128.into()),
+        deadline: None,
+        execution_mode: Some(ExecutionMode::BestEffort),
+    };
+    let res = wasm
+        .execute(
+            &env.contract_address,
+            &msg,
+            &[Coin::new(1000, "uosmo")],
+            &sender,
+        )
+        .unwrap();
+
+    let failed_event = res
+        .events
+        .iter()
+        .find(|event| event.ty == "wasm-swap_failed")
+        .expect("swap_failed event must be emitted");
+    let attribute = |key: &str| {
+        failed_event
+            .attributes
+            .iter()
+            .find(|attribute| attribute.key == key)
+            .map(|attribute| attribute.value.clone())
+            .unwrap()
+    };
+    assert_eq!(attribute("refund"), "1000uosmo");
+    // submessage errors are redacted to their codespace and code in replies.
+    assert!(!attribute("reason").is_empty());
+
+    // the input is sent back from the contract to the sender.
+    let refunded = res.events.iter().any(|event| {
+        let has = |key: &str, value: &str| {
+            event
+                .attributes
+                .iter()
+                .any(|attribute| attribute.key == key && attribute.value == value)
+        };
+        event.ty == "transfer"
+            && has("sender", &env.contract_address)
+            && has("recipient", &sender.address())
+            && has("amount", "1000uosmo")
+    });
+    assert!(refunded, "refund transfer must be emitted");
+
+    // the output is untouched and the contract holds nothing of the input.
+    let bank = Bank::new(&env.app);
+    let balances = bank
+        .query_all_balances(&QueryAllBalancesRequest {
+            address: sender.address(),
+            pagination: None,
+        })
+        .unwrap()
+        .balances;
+    assert_eq!(get_amount(&balances, "uion"), INITIAL_AMOUNT);
+
+    let contract_balances = bank
+        .query_all_balances(&QueryAllBalancesRequest {
+            address: env.contract_address.clone(),
+            pagination: None,
+        })
+        .unwrap()
+        .balances;
+    assert!(contract_balances.is_empty());
+}
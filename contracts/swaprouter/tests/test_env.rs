@@ -1,9 +1,18 @@
 use std::path::PathBuf;
 
-use cosmwasm_std::Coin;
-use osmosis_testing::{Account, OsmosisTestApp, SigningAccount};
-use osmosis_testing::{Gamm, Module, Wasm};
-use swaprouter::msg::InstantiateMsg;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Coin, Decimal};
+use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountInRoute;
+use osmosis_test_tube::{Account, OsmosisTestApp, RunnerResult, SigningAccount};
+use osmosis_test_tube::{Gamm, Module, Wasm};
+use swaprouter::msg::{ConfigUpdate, ExecuteMsg, InstantiateMsg, RouteEntry, SwapType};
+use swaprouter::state::RouteSegment;
+
+// instantiate message of the mock-oracle contract.
+#[cw_serde]
+struct MockOracleInstantiateMsg {
+    price: Decimal,
+}
 
 pub struct TestEnv {
     pub app: OsmosisTestApp,
@@ -51,7 +60,7 @@ impl TestEnv {
         .unwrap();
 
         let code_id = wasm
-            .store_code(&get_wasm(), None, &owner)
+            .store_code(&get_wasm("swaprouter"), None, &owner)
             .unwrap()
             .data
             .code_id;
@@ -61,6 +70,9 @@ impl TestEnv {
                 code_id,
                 &InstantiateMsg {
                     owner: owner.address(),
+                    config: ConfigUpdate::default(),
+                    routes: vec![],
+                    pair_configs: vec![],
                 },
                 Some(&owner.address()),
                 None,
@@ -79,13 +91,122 @@ impl TestEnv {
     }
 }
 
-fn get_wasm() -> Vec<u8> {
+// helpers shared by the test files, not every test file uses all of them.
+#[allow(dead_code)]
+impl TestEnv {
+    // setup_default_route sets the uosmo -> uion route
+    // through pool(2): uosmo/stake -> pool(3): stake/uion
+    pub fn setup_default_route(&self) {
+        let wasm = Wasm::new(&self.app);
+        wasm.execute(
+            &self.contract_address,
+            &ExecuteMsg::SetRoute {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+                pool_route: vec![
+                    RouteSegment::Hop(SwapAmountInRoute {
+                        pool_id: 2,
+                        token_out_denom: "stake".to_string(),
+                    }),
+                    RouteSegment::Hop(SwapAmountInRoute {
+                        pool_id: 3,
+                        token_out_denom: "uion".to_string(),
+                    }),
+                ],
+                bidirectional: false,
+                expires_at: None,
+                propagate: false,
+            },
+            &[],
+            &self.owner,
+        )
+        .expect("Setup route fixture must always succeed");
+    }
+
+    // instantiate_router instantiates another router contract with the given message.
+    pub fn instantiate_router(&self, msg: &InstantiateMsg) -> RunnerResult<String> {
+        let wasm = Wasm::new(&self.app);
+        let code_id = wasm
+            .store_code(&get_wasm("swaprouter"), None, &self.owner)?
+            .data
+            .code_id;
+
+        Ok(wasm
+            .instantiate(code_id, msg, None, None, &[], &self.owner)?
+            .data
+            .address)
+    }
+
+    // instantiate_mock_oracle instantiates an oracle contract
+    // returning the given price for every pair.
+    pub fn instantiate_mock_oracle(&self, price: Decimal) -> String {
+        let wasm = Wasm::new(&self.app);
+        let code_id = wasm
+            .store_code(&get_wasm("mock-oracle"), None, &self.owner)
+            .unwrap()
+            .data
+            .code_id;
+
+        wasm.instantiate(
+            code_id,
+            &MockOracleInstantiateMsg { price },
+            None,
+            None,
+            &[],
+            &self.owner,
+        )
+        .unwrap()
+        .data
+        .address
+    }
+
+    // swap_default_route swaps `amount` uosmo to uion on behalf of sender.
+    pub fn swap_default_route(&self, sender: &SigningAccount, amount: u128) {
+        let wasm = Wasm::new(&self.app);
+        wasm.execute(
+            &self.contract_address,
+            &ExecuteMsg::Swap {
+                input_coin: Coin::new(amount, "uosmo"),
+                output_denom: "uion".to_string(),
+                swap_type: SwapType::MinOutputAmount(1u128.into()),
+                deadline: None,
+                execution_mode: None,
+            },
+            &[Coin::new(amount, "uosmo")],
+            sender,
+        )
+        .expect("Swap must succeed");
+    }
+}
+
+// route_entry builds a non-expiring route entry from (pool_id, token_out_denom) hops.
+#[allow(dead_code)]
+pub fn route_entry(
+    input_denom: &str,
+    output_denom: &str,
+    pool_route: &[(u64, &str)],
+) -> RouteEntry {
+    RouteEntry {
+        input_denom: input_denom.to_string(),
+        output_denom: output_denom.to_string(),
+        pool_route: pool_route
+            .iter()
+            .map(|(pool_id, token_out_denom)| SwapAmountInRoute {
+                pool_id: *pool_id,
+                token_out_denom: token_out_denom.to_string(),
+            })
+            .collect(),
+        expires_at: None,
+    }
+}
+
+fn get_wasm(contract_name: &str) -> Vec<u8> {
     let wasm_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .join("..")
         .join("..")
         .join("target")
         .join("wasm32-unknown-unknown")
         .join("release")
-        .join("swaprouter.wasm");
+        .join(format!("{}.wasm", contract_name.replace('-', "_")));
     std::fs::read(wasm_path).unwrap()
 }